@@ -0,0 +1,58 @@
+use crate::error::Result;
+use crate::{export, lockfile, VarMap};
+
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// Returns `$XDG_CACHE_HOME/dotcraft/<key>.toml`, keyed by the image content and the options that
+/// shape the palette. The version is part of the key, since new releases may emit other variables.
+pub fn palette_path(wp_path: &Path, theme: &str, variant: &str, contrast: f64) -> Result<PathBuf> {
+    let cache_dir = match env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(
+            env::var("HOME").map_err(|err| format!("could not find home directory: {err}"))?,
+        )
+        .join(".cache"),
+    };
+    let image_hash = lockfile::hash_path(wp_path)?;
+    let key = lockfile::hash_bytes(
+        format!(
+            "{}:{image_hash}:{theme}:{variant}:{contrast}",
+            env!("CARGO_PKG_VERSION")
+        )
+        .as_bytes(),
+    );
+    Ok(cache_dir.join("dotcraft").join(format!("{key}.toml")))
+}
+
+/// Returns `None` if the cache is missing or unreadable, in which case the palette is regenerated.
+pub fn load_palette(path: &Path) -> Option<VarMap> {
+    let table: toml::Table = toml::from_str(&fs::read_to_string(path).ok()?).ok()?;
+    let mut config = VarMap::new();
+    for (key, value) in table {
+        let value = match value {
+            toml::Value::String(value) => value.into(),
+            toml::Value::Table(table) => {
+                let mut map = BTreeMap::new();
+                for (key, value) in table {
+                    map.insert(key, value.as_str()?.to_string());
+                }
+                map.into()
+            }
+            _ => return None,
+        };
+        config.insert(key, value);
+    }
+    Some(config)
+}
+
+pub fn save_palette(path: &Path, config: &VarMap) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|err| format!("could not create dir {}: {err}", parent.display()))?;
+    }
+    crate::write_atomic(path, export::export(config, "toml")?.as_bytes())
+}
@@ -7,51 +7,168 @@ use std::process::exit;
 #[derive(Debug)]
 pub struct Cli {
     pub manifest_path: PathBuf,
+    pub profile: Option<String>,
+    pub contrast: Option<f64>,
+    pub dry_run: bool,
     pub subcommand: SubCommand,
 }
 
 #[derive(Debug)]
 pub enum SubCommand {
-    Sync { force: bool, name: Option<String> },
-    Link { force: bool, name: Option<String> },
-    Generate { name: Option<String> },
+    Sync {
+        force: bool,
+        backup: bool,
+        name: Option<String>,
+    },
+    Link {
+        force: bool,
+        backup: bool,
+        name: Option<String>,
+    },
+    Generate {
+        force: bool,
+        backup: bool,
+        name: Option<String>,
+    },
+    Clean {
+        name: Option<String>,
+    },
+    Restore {
+        name: Option<String>,
+    },
+    Status {
+        name: Option<String>,
+    },
+    Colors {
+        format: Option<String>,
+    },
+    #[cfg(target_os = "linux")]
+    Watch {
+        link: bool,
+    },
+    Wallpaper {
+        force: bool,
+        backup: bool,
+        image: Option<PathBuf>,
+    },
 }
 
+#[cfg(target_os = "linux")]
 const USAGE: &str = "
 Usage: dotcraft [OPTION] <SUBCOMMAND>
 
 Options:
     -m, --manifest <FILE>  Path to Manifest file [default: ./Manifest.toml]
+    -p, --profile <NAME>   Apply the profile NAME from the Manifest
+    -c, --contrast <NUM>   Override the contrast level, from -1.0 to 1.0
+    -n, --dry-run          Print actions without touching the filesystem
     -h, --help             Print help
 
 Subcommands:
     sync                   Symlink files and generate templates 
     link                   Symlink files
-    generate               Generate templates";
+    generate               Generate templates
+    clean                  Remove symlinks
+    restore                Restore backed up files
+    status                 Show the state of every file
+    colors                 Preview or export the color palette
+    watch                  Regenerate templates when their sources change
+    wallpaper              Set the wallpaper and regenerate templates";
+
+// `watch` relies on inotify.
+#[cfg(not(target_os = "linux"))]
+const USAGE: &str = "
+Usage: dotcraft [OPTION] <SUBCOMMAND>
+
+Options:
+    -m, --manifest <FILE>  Path to Manifest file [default: ./Manifest.toml]
+    -p, --profile <NAME>   Apply the profile NAME from the Manifest
+    -c, --contrast <NUM>   Override the contrast level, from -1.0 to 1.0
+    -n, --dry-run          Print actions without touching the filesystem
+    -h, --help             Print help
+
+Subcommands:
+    sync                   Symlink files and generate templates 
+    link                   Symlink files
+    generate               Generate templates
+    clean                  Remove symlinks
+    restore                Restore backed up files
+    status                 Show the state of every file
+    colors                 Preview or export the color palette
+    wallpaper              Set the wallpaper and regenerate templates";
 
 const SYNC_USAGE: &str = "
 Usage: dotcraft sync [OPTION] [NAME]
 
 Options:
-    -f, --force  Force remove existing files
-    -h, --help   Print help";
+    -f, --force   Force remove existing files
+    -b, --backup  Back up existing files instead of removing them
+    -h, --help    Print help";
 
 const LINK_USAGE: &str = "
 Usage: dotcraft link [OPTION] [NAME]
 
 Options:
-    -f, --force  Force remove existing files
-    -h, --help   Print help";
+    -f, --force   Force remove existing files
+    -b, --backup  Back up existing files instead of removing them
+    -h, --help    Print help";
 
 const GENERATE_USAGE: &str = "
-Usage: dotcraft generate [NAME]
+Usage: dotcraft generate [OPTION] [NAME]
+
+Options:
+    -f, --force   Overwrite manually edited files
+    -b, --backup  Back up manually edited files before overwriting them
+    -h, --help    Print help";
+
+const CLEAN_USAGE: &str = "
+Usage: dotcraft clean [NAME]
 
 Options:
     -h, --help  Print help";
 
+const RESTORE_USAGE: &str = "
+Usage: dotcraft restore [NAME]
+
+Options:
+    -h, --help  Print help";
+
+const STATUS_USAGE: &str = "
+Usage: dotcraft status [NAME]
+
+Options:
+    -h, --help  Print help";
+
+const COLORS_USAGE: &str = "
+Usage: dotcraft colors [OPTION]
+
+Options:
+        --format <FORMAT>  Print the palette as json, toml, css, sh or yaml
+    -h, --help             Print help";
+
+#[cfg(target_os = "linux")]
+const WATCH_USAGE: &str = "
+Usage: dotcraft watch [OPTION]
+
+Options:
+    -l, --link  Also link files, picking up new files in recursive targets
+    -h, --help  Print help";
+
+const WALLPAPER_USAGE: &str = "
+Usage: dotcraft wallpaper [OPTION] <IMAGE>
+
+Options:
+    -f, --force   Overwrite manually edited files
+    -b, --backup  Back up manually edited files before overwriting them
+        --reset   Go back to the wallpaper set in the Manifest
+    -h, --help    Print help";
+
 impl Cli {
     pub fn try_parse() -> error::Result<Self> {
         let mut manifest_path = "./Manifest.toml".to_string();
+        let mut profile: Option<String> = None;
+        let mut contrast: Option<f64> = None;
+        let mut dry_run = false;
         let mut subcommand: Option<SubCommand> = None;
 
         let mut args = env::args();
@@ -71,12 +188,30 @@ impl Cli {
                             return Err(format!("missing required argument: FILE.\n{USAGE}").into());
                         }
                     }
+                    "-p" | "--profile" => {
+                        if let Some(name) = args.next() {
+                            profile = Some(name);
+                        } else {
+                            return Err(format!("missing required argument: NAME.\n{USAGE}").into());
+                        }
+                    }
+                    "-c" | "--contrast" => {
+                        if let Some(num) = args.next() {
+                            contrast = Some(num.parse().map_err(|err| {
+                                format!("invalid contrast {num}: {err}.\n{USAGE}")
+                            })?);
+                        } else {
+                            return Err(format!("missing required argument: NUM.\n{USAGE}").into());
+                        }
+                    }
+                    "-n" | "--dry-run" => dry_run = true,
                     _ => return Err(format!("invalid option {arg}.\n{USAGE}").into()),
                 }
             } else {
                 match arg.as_str() {
                     "sync" => {
                         let mut force = false;
+                        let mut backup = false;
                         let mut name: Option<String> = None;
                         for arg in args.by_ref() {
                             if arg.starts_with('-') {
@@ -88,6 +223,7 @@ impl Cli {
                                         exit(0);
                                     }
                                     "-f" | "--force" => force = true,
+                                    "-b" | "--backup" => backup = true,
                                     _ => {
                                         return Err(
                                             format!("invalid option {arg}.\n{SYNC_USAGE}").into()
@@ -98,10 +234,15 @@ impl Cli {
                                 name = Some(arg);
                             }
                         }
-                        subcommand = Some(SubCommand::Sync { force, name });
+                        subcommand = Some(SubCommand::Sync {
+                            force,
+                            backup,
+                            name,
+                        });
                     }
                     "link" => {
                         let mut force = false;
+                        let mut backup = false;
                         let mut name: Option<String> = None;
                         for arg in args.by_ref() {
                             if arg.starts_with('-') {
@@ -111,6 +252,7 @@ impl Cli {
                                         exit(0);
                                     }
                                     "-f" | "--force" => force = true,
+                                    "-b" | "--backup" => backup = true,
                                     _ => {
                                         return Err(
                                             format!("invalid option {arg}.\n{LINK_USAGE}").into()
@@ -121,9 +263,15 @@ impl Cli {
                                 name = Some(arg);
                             }
                         }
-                        subcommand = Some(SubCommand::Link { force, name });
+                        subcommand = Some(SubCommand::Link {
+                            force,
+                            backup,
+                            name,
+                        });
                     }
                     "generate" => {
+                        let mut force = false;
+                        let mut backup = false;
                         let mut name: Option<String> = None;
                         for arg in args.by_ref() {
                             if arg.starts_with('-') {
@@ -132,6 +280,8 @@ impl Cli {
                                         println!("Generate templates\n{GENERATE_USAGE}");
                                         exit(0);
                                     }
+                                    "-f" | "--force" => force = true,
+                                    "-b" | "--backup" => backup = true,
                                     _ => {
                                         return Err(format!(
                                             "invalid option {arg}.\n{GENERATE_USAGE}"
@@ -143,7 +293,170 @@ impl Cli {
                                 name = Some(arg);
                             }
                         }
-                        subcommand = Some(SubCommand::Generate { name });
+                        subcommand = Some(SubCommand::Generate {
+                            force,
+                            backup,
+                            name,
+                        });
+                    }
+                    "clean" => {
+                        let mut name: Option<String> = None;
+                        for arg in args.by_ref() {
+                            if arg.starts_with('-') {
+                                match arg.as_str() {
+                                    "-h" | "--help" => {
+                                        println!("Remove symlinks\n{CLEAN_USAGE}");
+                                        exit(0);
+                                    }
+                                    _ => {
+                                        return Err(
+                                            format!("invalid option {arg}.\n{CLEAN_USAGE}").into()
+                                        )
+                                    }
+                                }
+                            } else {
+                                name = Some(arg);
+                            }
+                        }
+                        subcommand = Some(SubCommand::Clean { name });
+                    }
+                    "restore" => {
+                        let mut name: Option<String> = None;
+                        for arg in args.by_ref() {
+                            if arg.starts_with('-') {
+                                match arg.as_str() {
+                                    "-h" | "--help" => {
+                                        println!("Restore backed up files\n{RESTORE_USAGE}");
+                                        exit(0);
+                                    }
+                                    _ => {
+                                        return Err(format!(
+                                            "invalid option {arg}.\n{RESTORE_USAGE}"
+                                        )
+                                        .into())
+                                    }
+                                }
+                            } else {
+                                name = Some(arg);
+                            }
+                        }
+                        subcommand = Some(SubCommand::Restore { name });
+                    }
+                    "status" => {
+                        let mut name: Option<String> = None;
+                        for arg in args.by_ref() {
+                            if arg.starts_with('-') {
+                                match arg.as_str() {
+                                    "-h" | "--help" => {
+                                        println!("Show the state of every file\n{STATUS_USAGE}");
+                                        exit(0);
+                                    }
+                                    _ => {
+                                        return Err(format!(
+                                            "invalid option {arg}.\n{STATUS_USAGE}"
+                                        )
+                                        .into())
+                                    }
+                                }
+                            } else {
+                                name = Some(arg);
+                            }
+                        }
+                        subcommand = Some(SubCommand::Status { name });
+                    }
+                    "colors" => {
+                        let mut format: Option<String> = None;
+                        while let Some(arg) = args.next() {
+                            match arg.as_str() {
+                                "-h" | "--help" => {
+                                    println!("Preview or export the color palette\n{COLORS_USAGE}");
+                                    exit(0);
+                                }
+                                "--format" => {
+                                    if let Some(value) = args.next() {
+                                        format = Some(value);
+                                    } else {
+                                        return Err(format!(
+                                            "missing required argument: FORMAT.\n{COLORS_USAGE}"
+                                        )
+                                        .into());
+                                    }
+                                }
+                                _ => {
+                                    return Err(
+                                        format!("invalid argument {arg}.\n{COLORS_USAGE}").into()
+                                    )
+                                }
+                            }
+                        }
+                        subcommand = Some(SubCommand::Colors { format });
+                    }
+                    #[cfg(target_os = "linux")]
+                    "watch" => {
+                        let mut link = false;
+                        for arg in args.by_ref() {
+                            match arg.as_str() {
+                                "-h" | "--help" => {
+                                    println!(
+                                        "Regenerate templates when their sources change\n{WATCH_USAGE}"
+                                    );
+                                    exit(0);
+                                }
+                                "-l" | "--link" => link = true,
+                                _ => {
+                                    return Err(
+                                        format!("invalid argument {arg}.\n{WATCH_USAGE}").into()
+                                    )
+                                }
+                            }
+                        }
+                        subcommand = Some(SubCommand::Watch { link });
+                    }
+                    #[cfg(not(target_os = "linux"))]
+                    "watch" => return Err("watch is only supported on Linux".into()),
+                    "wallpaper" => {
+                        let mut force = false;
+                        let mut backup = false;
+                        let mut reset = false;
+                        let mut image: Option<PathBuf> = None;
+                        for arg in args.by_ref() {
+                            if arg.starts_with('-') {
+                                match arg.as_str() {
+                                    "-h" | "--help" => {
+                                        println!(
+                                            "Set the wallpaper and regenerate templates\n{WALLPAPER_USAGE}"
+                                        );
+                                        exit(0);
+                                    }
+                                    "-f" | "--force" => force = true,
+                                    "-b" | "--backup" => backup = true,
+                                    "--reset" => reset = true,
+                                    _ => {
+                                        return Err(format!(
+                                            "invalid option {arg}.\n{WALLPAPER_USAGE}"
+                                        )
+                                        .into())
+                                    }
+                                }
+                            } else {
+                                // Relative to where dotcraft was run, not to the Manifest.
+                                image = Some(
+                                    std::path::absolute(&arg)
+                                        .map_err(|err| format!("invalid path {arg}: {err}"))?,
+                                );
+                            }
+                        }
+                        if image.is_none() && !reset {
+                            return Err(format!(
+                                "missing required argument: IMAGE.\n{WALLPAPER_USAGE}"
+                            )
+                            .into());
+                        }
+                        subcommand = Some(SubCommand::Wallpaper {
+                            force,
+                            backup,
+                            image: image.filter(|_| !reset),
+                        });
                     }
                     _ => return Err(format!("invalid subcommand {arg}.\n{USAGE}").into()),
                 }
@@ -153,6 +466,9 @@ impl Cli {
         if let Some(subcommand) = subcommand {
             Ok(Cli {
                 manifest_path: manifest_path.into(),
+                profile,
+                contrast,
+                dry_run,
                 subcommand,
             })
         } else {
@@ -3,22 +3,62 @@ use crate::VarMap;
 
 use material_colors::{
     color::Argb,
-    dynamic_color::Variant,
-    image::{FilterType, ImageReader},
+    dynamic_color::{DynamicScheme, Variant},
+    image::{AsPixels, FilterType, Image, ImageReader},
+    scheme::Scheme,
     theme::ThemeBuilder,
 };
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+    str::FromStr,
+};
+
+/// Returns the source color of the image along with its average lightness (L*, `0.0..=100.0`).
+pub fn extract_wallpaper_color(wp_path: &Path) -> Result<(Argb, f64)> {
+    let mut image = open_image(wp_path)?;
+    image.resize(128, 128, FilterType::Lanczos3);
+    let pixels = image.as_pixels();
+    let lightness =
+        pixels.iter().map(|pixel| pixel.as_lstar()).sum::<f64>() / pixels.len().max(1) as f64;
+    Ok((ImageReader::extract_color(&image), lightness))
+}
+
+/// `ImageReader::open` of material-colors panics on files it cannot decode, so images are decoded
+/// here instead.
+pub fn open_image(path: &Path) -> Result<Image> {
+    let image = image::ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|err| format!("could not read image {}: {err}", path.display()))?
+        .decode()
+        .map_err(|err| format!("could not decode image {}: {err}", path.display()))?;
+    Ok(Image::new(image.into_rgba8()))
+}
+
+/// Resolves `theme = "auto"` to "light" for bright colors and "dark" otherwise.
+pub fn resolve_theme(theme: &str, lightness: f64) -> &str {
+    match theme {
+        "auto" if lightness > 50.0 => "light",
+        "auto" => "dark",
+        _ => theme,
+    }
+}
+
+pub fn parse_color(color: &str) -> Result<Argb> {
+    Argb::from_str(color).map_err(|err| format!("invalid color {color}: {err}").into())
+}
 
 pub fn generate_material_colors(
-    wp_path: &Path,
+    source_color: Argb,
     theme: &str,
     variant: &str,
+    contrast: f64,
     config: &mut VarMap,
 ) -> Result<()> {
-    let mut image = ImageReader::open(wp_path)
-        .map_err(|err| format!("could not read image {}: {err}", wp_path.display()))?;
-    image.resize(128, 128, FilterType::Lanczos3);
-
+    if !(-1.0..=1.0).contains(&contrast) {
+        return Err(format!("invalid contrast {contrast}\nPossible values: -1.0 to 1.0").into());
+    }
     let variant = match variant {
         "monochrome" => Variant::Monochrome,
         "neutral" => Variant::Neutral,
@@ -32,63 +72,211 @@ pub fn generate_material_colors(
         _ => return Err(format!("invalid variant {variant}\nPossible values: \"monochrome\", \"neutral\", \"tonal_spot\", \"vibrant\", \"expressive\", \"fidelity\", \"content\", \"rainbow\", \"fruit_salad\"").into()),
     };
 
-    let color_palette = ThemeBuilder::with_source(ImageReader::extract_color(&image))
+    // `ThemeBuilder` always uses the default contrast, so the schemes are built separately.
+    let dark: BTreeMap<String, String> = Scheme::from(DynamicScheme::by_variant(
+        source_color,
+        &variant,
+        true,
+        Some(contrast),
+    ))
+    .into_iter()
+    .map(|(k, v)| (k, v.to_hex()))
+    .collect();
+    let light: BTreeMap<String, String> = Scheme::from(DynamicScheme::by_variant(
+        source_color,
+        &variant,
+        false,
+        Some(contrast),
+    ))
+    .into_iter()
+    .map(|(k, v)| (k, v.to_hex()))
+    .collect();
+
+    let color_palette = ThemeBuilder::with_source(source_color)
         .variant(variant)
         .build();
 
-    config.insert("source_color".to_string(), color_palette.source.to_hex());
+    config.insert(
+        "source_color".to_string(),
+        color_palette.source.to_hex().into(),
+    );
 
-    match theme {
-        "dark" => {
-            for (k, v) in color_palette.schemes.dark.into_iter() {
-                config.insert(k, v.to_hex());
-            }
-        }
-        "light" => {
-            for (k, v) in color_palette.schemes.light.into_iter() {
-                config.insert(k, v.to_hex());
-            }
-        }
+    let scheme = match theme {
+        "dark" => &dark,
+        "light" => &light,
         _ => {
-            return Err(
-                format!("invalid theme {theme}\nPossible values: \"dark\", \"light\"").into(),
+            return Err(format!(
+                "invalid theme {theme}\nPossible values: \"dark\", \"light\", \"auto\""
             )
+            .into())
+        }
+    };
+    for (k, v) in scheme {
+        config.insert(k.clone(), v.clone().into());
+    }
+    config.insert("dark".to_string(), dark.into());
+    config.insert("light".to_string(), light.into());
+
+    let palettes = [
+        ("primary", &color_palette.palettes.primary),
+        ("secondary", &color_palette.palettes.secondary),
+        ("tertiary", &color_palette.palettes.tertiary),
+        ("neutral", &color_palette.palettes.neutral),
+        ("neutral_variant", &color_palette.palettes.neutral_variant),
+        ("error", &color_palette.palettes.error),
+    ];
+    for (name, palette) in palettes {
+        for tone in 0..=100 {
+            config.insert(
+                format!("{name}_tone_{tone}"),
+                palette.tone(tone).to_hex().into(),
+            );
         }
     }
 
     generate_base16_colors(config, &color_palette.source);
-    config.insert("theme".to_string(), theme.to_string());
+    config.insert("theme".to_string(), theme.into());
+    Ok(())
+}
+
+/// Loads `base00`..`base0F` from a base16 scheme file. The base24 `base10`..`base17` extras end up
+/// in a nested `base24` map.
+///
+/// Both the classic flat layout and the newer one with a nested `palette` map are accepted.
+pub fn load_base16_scheme(path: &Path, config: &mut VarMap) -> Result<()> {
+    let data = fs::read_to_string(path)
+        .map_err(|err| format!("could not read file {}: {err}", path.display()))?;
+
+    let mut colors: HashMap<String, String> = HashMap::new();
+    for line in data.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim();
+        let value = value.split(" #").next().unwrap_or_default().trim();
+        let value = value.trim_matches(|c| c == '"' || c == '\'');
+        if key.len() == 6 && key.starts_with("base") {
+            let color = parse_color(value)
+                .map_err(|err| format!("invalid scheme {}: {err}", path.display()))?;
+            colors.insert(format!("base{}", key[4..].to_uppercase()), color.to_hex());
+        } else if matches!(key, "scheme" | "name") {
+            config.insert("scheme".to_string(), value.into());
+        }
+    }
+
+    for i in 0..16 {
+        let key = format!("base{i:02X}");
+        let Some(hex) = colors.remove(&key) else {
+            return Err(format!("invalid scheme {}: missing {key}", path.display()).into());
+        };
+        insert_base16(config, i, hex);
+    }
+    // The base24 extras would clash with the decimal aliases, so they get their own map.
+    if !colors.is_empty() {
+        config.insert(
+            "base24".to_string(),
+            colors.into_iter().collect::<BTreeMap<_, _>>().into(),
+        );
+    }
     Ok(())
 }
 
-pub fn generate_base16_colors(config: &mut HashMap<String, String>, source_color: &Argb) {
-    let base16: [(&str, &Argb); 16] = [
-        ("base0", &Argb::new(255, 0, 0, 0)),
-        ("base1", &Argb::new(255, 128, 0, 0)),
-        ("base2", &Argb::new(255, 0, 128, 0)),
-        ("base3", &Argb::new(255, 128, 128, 0)),
-        ("base4", &Argb::new(255, 0, 0, 128)),
-        ("base5", &Argb::new(255, 128, 0, 128)),
-        ("base6", &Argb::new(255, 0, 128, 128)),
-        ("base7", &Argb::new(255, 192, 192, 192)),
-        ("base8", &Argb::new(255, 128, 128, 128)),
-        ("base9", &Argb::new(255, 255, 0, 0)),
-        ("base10", &Argb::new(255, 0, 255, 0)),
-        ("base11", &Argb::new(255, 255, 255, 0)),
-        ("base12", &Argb::new(255, 0, 0, 255)),
-        ("base13", &Argb::new(255, 255, 0, 255)),
-        ("base14", &Argb::new(255, 0, 255, 255)),
-        ("base15", &Argb::new(255, 255, 255, 255)),
+pub fn generate_base16_colors(config: &mut VarMap, source_color: &Argb) {
+    let base16: [Argb; 16] = [
+        Argb::new(255, 0, 0, 0),
+        Argb::new(255, 128, 0, 0),
+        Argb::new(255, 0, 128, 0),
+        Argb::new(255, 128, 128, 0),
+        Argb::new(255, 0, 0, 128),
+        Argb::new(255, 128, 0, 128),
+        Argb::new(255, 0, 128, 128),
+        Argb::new(255, 192, 192, 192),
+        Argb::new(255, 128, 128, 128),
+        Argb::new(255, 255, 0, 0),
+        Argb::new(255, 0, 255, 0),
+        Argb::new(255, 255, 255, 0),
+        Argb::new(255, 0, 0, 255),
+        Argb::new(255, 255, 0, 255),
+        Argb::new(255, 0, 255, 255),
+        Argb::new(255, 255, 255, 255),
     ];
-    for (name, value) in base16.into_iter() {
-        let new_color = blend_color(value, source_color);
-        config.insert(name.to_string(), new_color.to_hex());
+    for (i, value) in base16.iter().enumerate() {
+        insert_base16(config, i, blend_color(value, source_color).to_hex());
     }
 }
 
+/// Inserts `base00`..`base0F` along with the decimal `base0`..`base15` aliases, so that templates
+/// see the same names whether the colors come from a scheme file or are generated.
+fn insert_base16(config: &mut VarMap, index: usize, hex: String) {
+    config.insert(format!("base{index}"), hex.clone().into());
+    config.insert(format!("base{index:02X}"), hex.into());
+}
+
 fn blend_color(first: &Argb, second: &Argb) -> Argb {
     let r = (first.red as f32 * 0.5 + second.red as f32 * 0.5) as u8;
     let g = (first.green as f32 * 0.5 + second.green as f32 * 0.5) as u8;
     let b = (first.blue as f32 * 0.5 + second.blue as f32 * 0.5) as u8;
     Argb::new(255, r, g, b)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(name: &str, data: &str) -> Result<VarMap> {
+        let path =
+            std::env::temp_dir().join(format!("dotcraft-{}-{name}.yaml", std::process::id()));
+        fs::write(&path, data).unwrap();
+        let mut config = VarMap::new();
+        let result = load_base16_scheme(&path, &mut config);
+        fs::remove_file(&path).unwrap();
+        result.map(|()| config)
+    }
+
+    fn get<'a>(config: &'a VarMap, key: &str) -> &'a str {
+        match &config[key] {
+            upon::Value::String(value) => value,
+            value => panic!("{key} is not a string: {value:?}"),
+        }
+    }
+
+    #[test]
+    fn flat_scheme() {
+        let mut data = String::from("scheme: \"Test\"\nauthor: \"someone\"\n");
+        for i in 0..16 {
+            data.push_str(&format!(
+                "base{i:02X}: \"{i:02x}{i:02x}{i:02x}\" # comment\n"
+            ));
+        }
+        let config = load("flat", &data).unwrap();
+        assert_eq!(get(&config, "scheme"), "Test");
+        assert_eq!(get(&config, "base00"), "000000");
+        assert_eq!(get(&config, "base0A"), "0a0a0a");
+        assert_eq!(get(&config, "base10"), "0a0a0a");
+        assert_eq!(get(&config, "base15"), "0f0f0f");
+        assert!(!config.contains_key("base24"));
+    }
+
+    #[test]
+    fn nested_base24_scheme() {
+        let mut data = String::from("name: 'Test'\npalette:\n");
+        for i in 0..24 {
+            data.push_str(&format!("  base{i:02x}: '#{i:02x}0000'\n"));
+        }
+        let config = load("nested", &data).unwrap();
+        assert_eq!(get(&config, "scheme"), "Test");
+        assert_eq!(get(&config, "base0F"), "0f0000");
+        assert_eq!(get(&config, "base10"), "0a0000");
+        let upon::Value::Map(base24) = &config["base24"] else {
+            panic!("base24 is not a map");
+        };
+        assert_eq!(base24.len(), 8);
+        assert_eq!(base24["base17"], upon::Value::String("170000".to_string()));
+    }
+
+    #[test]
+    fn incomplete_scheme() {
+        let err = load("incomplete", "base00: \"000000\"\n").unwrap_err();
+        assert!(err.to_string().ends_with("missing base01"), "{err}");
+    }
+}
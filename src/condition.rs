@@ -0,0 +1,116 @@
+use serde::Deserialize;
+use std::{env, fs, os::unix::fs::PermissionsExt, path::Path};
+
+/// Every field that is set has to match for the condition to be met.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Condition {
+    os: Option<String>,
+    hostname: Option<String>,
+    env: Option<String>,
+    command: Option<String>,
+}
+
+impl Condition {
+    pub fn is_met(&self) -> bool {
+        if let Some(os) = &self.os {
+            if os != env::consts::OS {
+                return false;
+            }
+        }
+        if let Some(pattern) = &self.hostname {
+            if !hostname().is_some_and(|hostname| glob_match(pattern, &hostname)) {
+                return false;
+            }
+        }
+        if let Some(var) = &self.env {
+            if env::var_os(var).is_none_or(|value| value.is_empty()) {
+                return false;
+            }
+        }
+        if let Some(command) = &self.command {
+            if !command_exists(command) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+pub fn hostname() -> Option<String> {
+    let hostname = fs::read_to_string("/proc/sys/kernel/hostname")
+        .or_else(|_| fs::read_to_string("/etc/hostname"))
+        .ok()
+        .or_else(|| env::var("HOSTNAME").ok())?;
+    Some(hostname.trim().to_string())
+}
+
+fn command_exists(command: &str) -> bool {
+    if command.contains('/') {
+        return is_executable(Path::new(command));
+    }
+    env::var_os("PATH")
+        .is_some_and(|paths| env::split_paths(&paths).any(|dir| is_executable(&dir.join(command))))
+}
+
+fn is_executable(path: &Path) -> bool {
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+/// Matches `text` against `pattern` where `*` matches any sequence and `?` any single character.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob() {
+        let cases = [
+            ("work-*", "work-laptop", true),
+            ("work-*", "work-", true),
+            ("work-*", "home-laptop", false),
+            ("*-laptop", "work-laptop", true),
+            ("w?rk", "work", true),
+            ("w?rk", "wrk", false),
+            ("*a*b", "xaxxab", true),
+            ("*a*b", "xaxxa", false),
+            ("**", "", true),
+            ("", "", true),
+            ("", "a", false),
+            ("laptop", "laptop", true),
+            ("laptop", "laptop2", false),
+        ];
+        for (pattern, text, expected) in cases {
+            assert_eq!(glob_match(pattern, text), expected, "{pattern} {text}");
+        }
+    }
+}
@@ -2,6 +2,7 @@ use std::io;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+#[derive(Debug)]
 pub struct Error {
     ctx: String,
 }
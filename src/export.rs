@@ -0,0 +1,135 @@
+use crate::error::Result;
+use crate::VarMap;
+
+use std::fmt::Write;
+
+type Entries<'a> = Vec<(&'a str, &'a str)>;
+
+/// Serializes the palette. Nested maps like `dark` and `light` stay nested where the format allows
+/// it and are flattened to `dark_primary` (`--dark-primary` in CSS) otherwise.
+pub fn export(config: &VarMap, format: &str) -> Result<String> {
+    let (flat, nested) = collect(config);
+    let mut out = String::new();
+    match format {
+        "json" => {
+            let mut fields: Vec<String> = flat
+                .iter()
+                .map(|(key, value)| format!("  {}: {}", quote(key), quote(value)))
+                .collect();
+            for (name, entries) in nested.iter() {
+                let inner: Vec<String> = entries
+                    .iter()
+                    .map(|(key, value)| format!("    {}: {}", quote(key), quote(value)))
+                    .collect();
+                fields.push(format!("  {}: {{\n{}\n  }}", quote(name), inner.join(",\n")));
+            }
+            let _ = writeln!(out, "{{\n{}\n}}", fields.join(",\n"));
+        }
+        "yaml" => {
+            for (key, value) in flat.iter() {
+                let _ = writeln!(out, "{key}: {}", quote(value));
+            }
+            for (name, entries) in nested.iter() {
+                let _ = writeln!(out, "{name}:");
+                for (key, value) in entries.iter() {
+                    let _ = writeln!(out, "  {key}: {}", quote(value));
+                }
+            }
+        }
+        "toml" => {
+            let mut table = toml::Table::new();
+            for (key, value) in flat.iter() {
+                table.insert(key.to_string(), value.to_string().into());
+            }
+            for (name, entries) in nested.iter() {
+                let inner: toml::Table = entries
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string().into()))
+                    .collect();
+                table.insert(name.to_string(), inner.into());
+            }
+            out = toml::to_string(&table)
+                .map_err(|err| format!("could not serialize palette: {err}"))?;
+        }
+        "css" => {
+            out.push_str(":root {\n");
+            for (key, value) in flatten(&flat, &nested) {
+                let key = key.replace('_', "-");
+                if is_hex(value) {
+                    let _ = writeln!(out, "  --{key}: #{value};");
+                } else {
+                    let _ = writeln!(out, "  --{key}: {};", quote(value));
+                }
+            }
+            out.push_str("}\n");
+        }
+        "sh" => {
+            for (key, value) in flatten(&flat, &nested) {
+                let _ = writeln!(out, "export {key}='{}'", value.replace('\'', "'\\''"));
+            }
+        }
+        _ => return Err(format!("invalid format {format}\nPossible values: \"json\", \"toml\", \"css\", \"sh\", \"yaml\"").into()),
+    }
+    Ok(out)
+}
+
+/// Splits the string values from the nested maps, both sorted by key.
+fn collect(config: &VarMap) -> (Entries<'_>, Vec<(&str, Entries<'_>)>) {
+    let mut flat = Vec::new();
+    let mut nested = Vec::new();
+    for (key, value) in config.iter() {
+        match value {
+            upon::Value::String(value) => flat.push((key.as_str(), value.as_str())),
+            upon::Value::Map(map) => {
+                let entries: Entries = map
+                    .iter()
+                    .filter_map(|(key, value)| match value {
+                        upon::Value::String(value) => Some((key.as_str(), value.as_str())),
+                        _ => None,
+                    })
+                    .collect();
+                nested.push((key.as_str(), entries));
+            }
+            _ => {}
+        }
+    }
+    flat.sort();
+    nested.sort();
+    (flat, nested)
+}
+
+fn flatten<'a>(flat: &Entries<'a>, nested: &[(&'a str, Entries<'a>)]) -> Vec<(String, &'a str)> {
+    let mut entries: Vec<(String, &str)> = flat
+        .iter()
+        .map(|(key, value)| (key.to_string(), *value))
+        .collect();
+    for (name, inner) in nested.iter() {
+        for (key, value) in inner.iter() {
+            entries.push((format!("{name}_{key}"), *value));
+        }
+    }
+    entries
+}
+
+fn is_hex(value: &str) -> bool {
+    value.len() == 6 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Double-quoted string that is valid in both JSON and YAML.
+fn quote(value: &str) -> String {
+    let mut out = String::from('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
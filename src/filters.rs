@@ -0,0 +1,231 @@
+use material_colors::color::Argb;
+use std::str::FromStr;
+use upon::Value;
+
+type Result<T> = std::result::Result<T, String>;
+
+/// Registers the color filters. Every filter accepts `rrggbb` or `rrggbbaa` with an optional `#`,
+/// the way CSS and GTK read 8-digit hex.
+pub fn register(engine: &mut upon::Engine) {
+    engine.add_filter("lighten", |color: &str, amount: Value| -> Result<String> {
+        let amount = to_amount(&amount)?;
+        map_hsl(color, |h, s, l| (h, s, l + amount))
+    });
+    engine.add_filter("darken", |color: &str, amount: Value| -> Result<String> {
+        let amount = to_amount(&amount)?;
+        map_hsl(color, |h, s, l| (h, s, l - amount))
+    });
+    engine.add_filter("saturate", |color: &str, amount: Value| -> Result<String> {
+        let amount = to_amount(&amount)?;
+        map_hsl(color, |h, s, l| (h, s + amount, l))
+    });
+    engine.add_filter("alpha", |color: &str, amount: Value| -> Result<String> {
+        let mut argb = parse(color)?;
+        argb.alpha = to_channel(to_amount(&amount)?);
+        Ok(format_like(color, argb))
+    });
+    engine.add_filter(
+        "mix",
+        |color: &str, other: &str, weight: Value| -> Result<String> {
+            let (first, second) = (parse(color)?, parse(other)?);
+            let weight = to_amount(&weight)?.clamp(0.0, 1.0);
+            let blend =
+                |a: u8, b: u8| (a as f64 * (1.0 - weight) + b as f64 * weight).round() as u8;
+            let mixed = Argb::new(
+                blend(first.alpha, second.alpha),
+                blend(first.red, second.red),
+                blend(first.green, second.green),
+                blend(first.blue, second.blue),
+            );
+            Ok(format_like(color, mixed))
+        },
+    );
+    engine.add_filter("invert", |color: &str| -> Result<String> {
+        let argb = parse(color)?;
+        let inverted = Argb::new(
+            argb.alpha,
+            255 - argb.red,
+            255 - argb.green,
+            255 - argb.blue,
+        );
+        Ok(format_like(color, inverted))
+    });
+    engine.add_filter("to_rgb", |color: &str| -> Result<String> {
+        let argb = parse(color)?;
+        Ok(format!("rgb({}, {}, {})", argb.red, argb.green, argb.blue))
+    });
+    engine.add_filter("to_rgba", |color: &str| -> Result<String> {
+        let argb = parse(color)?;
+        Ok(format!(
+            "rgba({}, {}, {}, {:.2})",
+            argb.red,
+            argb.green,
+            argb.blue,
+            argb.alpha as f64 / 255.0
+        ))
+    });
+    engine.add_filter("to_hsl", |color: &str| -> Result<String> {
+        let (h, s, l) = to_hsl(parse(color)?);
+        Ok(format!(
+            "hsl({}, {}%, {}%)",
+            h.round(),
+            (s * 100.0).round(),
+            (l * 100.0).round()
+        ))
+    });
+    engine.add_filter("strip_hash", |color: &str| -> String {
+        color.trim_start_matches('#').to_string()
+    });
+    engine.add_filter("to_0x", |color: &str| -> Result<String> {
+        let argb = parse(color)?;
+        Ok(format!(
+            "0x{:02x}{:02x}{:02x}{:02x}",
+            argb.alpha, argb.red, argb.green, argb.blue
+        ))
+    });
+}
+
+fn parse(color: &str) -> Result<Argb> {
+    let hex = color.trim_start_matches('#');
+    let (rgb, alpha) = match hex.len() {
+        8 => hex.split_at(6),
+        _ => (hex, "ff"),
+    };
+    let mut argb = Argb::from_str(rgb).map_err(|err| format!("invalid color {color}: {err}"))?;
+    argb.alpha =
+        u8::from_str_radix(alpha, 16).map_err(|err| format!("invalid color {color}: {err}"))?;
+    Ok(argb)
+}
+
+fn to_amount(value: &Value) -> Result<f64> {
+    match value {
+        Value::Float(amount) => Ok(*amount),
+        Value::Integer(amount) => Ok(*amount as f64),
+        _ => Err("expected a number".to_string()),
+    }
+}
+
+fn to_channel(amount: f64) -> u8 {
+    (amount.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Formats `argb` the way `original` was written: with or without `#`, alpha only when not opaque.
+/// The alpha comes last, as in `rrggbbaa`.
+fn format_like(original: &str, argb: Argb) -> String {
+    let hash = if original.starts_with('#') { "#" } else { "" };
+    if argb.alpha == 255 {
+        format!("{hash}{:02x}{:02x}{:02x}", argb.red, argb.green, argb.blue)
+    } else {
+        format!(
+            "{hash}{:02x}{:02x}{:02x}{:02x}",
+            argb.red, argb.green, argb.blue, argb.alpha
+        )
+    }
+}
+
+fn map_hsl(color: &str, f: impl Fn(f64, f64, f64) -> (f64, f64, f64)) -> Result<String> {
+    let argb = parse(color)?;
+    let (h, s, l) = to_hsl(argb);
+    let (h, s, l) = f(h, s, l);
+    let (r, g, b) = from_hsl(h, s.clamp(0.0, 1.0), l.clamp(0.0, 1.0));
+    Ok(format_like(color, Argb::new(argb.alpha, r, g, b)))
+}
+
+/// Returns hue in degrees and saturation and lightness in `0.0..=1.0`.
+fn to_hsl(argb: Argb) -> (f64, f64, f64) {
+    let (r, g, b) = (
+        argb.red as f64 / 255.0,
+        argb.green as f64 / 255.0,
+        argb.blue as f64 / 255.0,
+    );
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let l = (max + min) / 2.0;
+    let delta = max - min;
+    if delta == 0.0 {
+        return (0.0, 0.0, l);
+    }
+    let s = delta / (1.0 - (2.0 * l - 1.0).abs());
+    let h = if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    (h, s, l)
+}
+
+fn from_hsl(h: f64, s: f64, l: f64) -> (u8, u8, u8) {
+    let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let x = c * (1.0 - ((h / 60.0).rem_euclid(2.0) - 1.0).abs());
+    let m = l - c / 2.0;
+    let (r, g, b) = match h.rem_euclid(360.0) as u32 / 60 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let channel = |v: f64| ((v + m) * 255.0).round() as u8;
+    (channel(r), channel(g), channel(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str) -> String {
+        let mut engine = upon::Engine::new();
+        register(&mut engine);
+        engine
+            .compile(template)
+            .unwrap()
+            .render(&engine, upon::value! {})
+            .to_string()
+            .unwrap()
+    }
+
+    #[test]
+    fn hsl_round_trip() {
+        let cases = [
+            ((255, 0, 0), (0.0, 1.0, 0.5)),
+            ((0, 255, 0), (120.0, 1.0, 0.5)),
+            ((0, 0, 255), (240.0, 1.0, 0.5)),
+            ((255, 255, 255), (0.0, 0.0, 1.0)),
+            ((128, 128, 128), (0.0, 0.0, 128.0 / 255.0)),
+        ];
+        for ((r, g, b), (h, s, l)) in cases {
+            let hsl = to_hsl(Argb::new(255, r, g, b));
+            assert!((hsl.0 - h).abs() < 1e-9, "{hsl:?}");
+            assert!((hsl.1 - s).abs() < 1e-9, "{hsl:?}");
+            assert!((hsl.2 - l).abs() < 1e-9, "{hsl:?}");
+            assert_eq!(from_hsl(h, s, l), (r, g, b));
+        }
+        assert_eq!(from_hsl(30.0, 0.5, 0.5), (191, 128, 64));
+    }
+
+    #[test]
+    fn alpha_comes_last() {
+        assert_eq!(render(r##"{{ "#4285f4" | alpha: 0.5 }}"##), "#4285f480");
+        assert_eq!(render(r##"{{ "4285f4" | alpha: 1 }}"##), "4285f4");
+        assert_eq!(
+            render(r##"{{ "4285f4" | alpha: 0.5 | to_rgba }}"##),
+            "rgba(66, 133, 244, 0.50)"
+        );
+        assert_eq!(
+            render(r##"{{ "4285f4" | alpha: 0.5 | to_0x }}"##),
+            "0x804285f4"
+        );
+    }
+
+    #[test]
+    fn color_filters() {
+        assert_eq!(render(r##"{{ "#000000" | lighten: 0.5 }}"##), "#808080");
+        assert_eq!(render(r##"{{ "ffffff" | darken: 1 }}"##), "000000");
+        assert_eq!(render(r##"{{ "000000" | mix: "ffffff", 0.5 }}"##), "808080");
+        assert_eq!(render(r##"{{ "#102030" | invert }}"##), "#efdfcf");
+        assert_eq!(render(r##"{{ "ff0000" | to_hsl }}"##), "hsl(0, 100%, 50%)");
+    }
+}
@@ -0,0 +1,158 @@
+use crate::error::Result;
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const HEADER: &str = "# This file is automatically generated by dotcraft.\n# It is not intended for manual editing.\n\n";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Lockfile {
+    #[serde(skip)]
+    path: PathBuf,
+    #[serde(skip)]
+    changed: bool,
+    /// Set by `dotcraft wallpaper`, overrides the wallpaper of the Manifest.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wallpaper: Option<PathBuf>,
+    #[serde(default)]
+    pub entries: IndexMap<PathBuf, Entry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    pub kind: Kind,
+    pub source: PathBuf,
+    pub hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Link,
+    Copy,
+    Generated,
+}
+
+impl Lockfile {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut lockfile = if path.exists() {
+            toml::from_str(
+                &fs::read_to_string(path)
+                    .map_err(|err| format!("could not read file {}: {err}", path.display()))?,
+            )
+            .map_err(|err| format!("could not parse toml {}: {err}", path.display()))?
+        } else {
+            Lockfile::default()
+        };
+        lockfile.path = path.to_path_buf();
+        Ok(lockfile)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+        let data = toml::to_string(self)
+            .map_err(|err| format!("could not serialize {}: {err}", self.path.display()))?;
+        crate::write_atomic(&self.path, format!("{HEADER}{data}").as_bytes())
+    }
+
+    pub fn set_wallpaper(&mut self, wallpaper: Option<PathBuf>) {
+        if self.wallpaper != wallpaper {
+            self.wallpaper = wallpaper;
+            self.changed = true;
+        }
+    }
+
+    pub fn get(&self, dest: impl AsRef<Path>) -> Option<&Entry> {
+        let dest = std::path::absolute(dest).ok()?;
+        self.entries.get(&dest)
+    }
+
+    pub fn insert(&mut self, dest: impl AsRef<Path>, entry: Entry) -> Result<()> {
+        let dest = std::path::absolute(dest.as_ref())
+            .map_err(|err| format!("invalid path {}: {err}", dest.as_ref().display()))?;
+        if self.entries.get(&dest) != Some(&entry) {
+            self.entries.insert(dest, entry);
+            self.changed = true;
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, dest: impl AsRef<Path>) -> Option<Entry> {
+        let dest = std::path::absolute(dest).ok()?;
+        let entry = self.entries.shift_remove(&dest);
+        if entry.is_some() {
+            self.changed = true;
+        }
+        entry
+    }
+
+    /// Returns the entries whose name is not accepted by `exists`.
+    pub fn orphans(&self, kind: Kind, exists: impl Fn(&str) -> bool) -> Vec<(PathBuf, Entry)> {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| entry.kind == kind && !exists(&entry.name))
+            .map(|(dest, entry)| (dest.clone(), entry.clone()))
+            .collect()
+    }
+}
+
+pub fn hash_path(path: impl AsRef<Path>) -> Result<String> {
+    let path = path.as_ref();
+    let mut hasher = Hasher::new();
+    hash_path_into(path, &mut hasher)?;
+    Ok(hasher.finish())
+}
+
+pub fn hash_bytes(data: &[u8]) -> String {
+    let mut hasher = Hasher::new();
+    hasher.update(data);
+    hasher.finish()
+}
+
+fn hash_path_into(path: &Path, hasher: &mut Hasher) -> Result<()> {
+    if path.is_dir() {
+        let mut entries = fs::read_dir(path)
+            .map_err(|err| format!("could not read dir {}: {err}", path.display()))?
+            .collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            hasher.update(entry.file_name().as_encoded_bytes());
+            hash_path_into(&entry.path(), hasher)?;
+        }
+    } else {
+        let data = fs::read(path)
+            .map_err(|err| format!("could not read file {}: {err}", path.display()))?;
+        hasher.update(&data);
+    }
+    Ok(())
+}
+
+/// 64-bit FNV-1a. Stable across builds, unlike `std::hash::DefaultHasher`.
+struct Hasher(u64);
+
+impl Hasher {
+    fn new() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for byte in data {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn finish(&self) -> String {
+        format!("{:016x}", self.0)
+    }
+}
@@ -1,25 +1,104 @@
+mod cache;
 mod cli;
 mod colors;
+mod condition;
 mod error;
+mod export;
+mod filters;
+mod lockfile;
+#[cfg(target_os = "linux")]
+mod watch;
 
+use condition::Condition;
 use indexmap::IndexMap;
+use lockfile::Lockfile;
 use serde::Deserialize;
 use std::{
     collections::HashMap,
-    fs, io,
+    fs,
+    io::{self, Write},
     os::unix::fs::symlink,
     path::{Component, Path, PathBuf},
-    process::exit,
+    process::{exit, Command},
 };
 
 #[derive(Debug, Deserialize)]
 struct Manifest {
     wallpaper: Option<PathBuf>,
+    source_color: Option<String>,
+    scheme: Option<PathBuf>,
     #[serde(default = "default_theme_option")]
     theme: String,
     #[serde(default = "default_variant_option")]
     variant: String,
+    #[serde(default = "default_contrast_option")]
+    contrast: f64,
+    #[serde(default = "default_relative_option")]
+    relative: bool,
+    #[serde(default = "default_backup_option")]
+    backup: bool,
+    backup_dir: Option<PathBuf>,
+    #[serde(default)]
+    hooks: Hooks,
+    #[serde(default)]
+    palettes: IndexMap<String, Palette>,
     files: IndexMap<String, File>,
+    #[serde(default)]
+    profiles: IndexMap<String, Overlay>,
+    #[serde(default)]
+    hosts: IndexMap<String, Overlay>,
+    #[serde(skip)]
+    root: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Overlay {
+    wallpaper: Option<PathBuf>,
+    source_color: Option<String>,
+    scheme: Option<PathBuf>,
+    theme: Option<String>,
+    variant: Option<String>,
+    contrast: Option<f64>,
+    #[serde(default)]
+    files: IndexMap<String, FileOverlay>,
+}
+
+/// A color source of its own, picked by files with `palette = "<name>"`. `theme`, `variant` and
+/// `contrast` default to the ones of the Manifest.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Palette {
+    wallpaper: Option<PathBuf>,
+    source_color: Option<String>,
+    scheme: Option<PathBuf>,
+    theme: Option<String>,
+    variant: Option<String>,
+    contrast: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum FileOverlay {
+    Enabled(bool),
+    Patch(Box<FilePatch>),
+}
+
+/// Fields of an overlay entry. They are merged into the entry of the same name, or make up a new
+/// one, which then needs a `dest`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FilePatch {
+    target: Option<PathBuf>,
+    dest: Option<PathBuf>,
+    template: Option<PathBuf>,
+    recursive: Option<bool>,
+    mode: Option<Mode>,
+    relative: Option<bool>,
+    palette: Option<String>,
+    #[serde(alias = "if")]
+    when: Option<Condition>,
+    hooks: Option<FileHooks>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -29,9 +108,47 @@ struct File {
     template: Option<PathBuf>,
     #[serde(default = "default_recursive_option")]
     recursive: bool,
+    #[serde(default = "default_mode_option")]
+    mode: Mode,
+    relative: Option<bool>,
+    palette: Option<String>,
+    #[serde(alias = "if")]
+    when: Option<Condition>,
+    #[serde(default)]
+    hooks: FileHooks,
 }
 
-type VarMap = HashMap<String, String>;
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileHooks {
+    post_link: Option<String>,
+    post_generate: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Hooks {
+    pre_sync: Option<String>,
+    post_sync: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Mode {
+    Link,
+    Copy,
+}
+
+type VarMap = HashMap<String, upon::Value>;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Options<'a> {
+    force: bool,
+    dry_run: bool,
+    relative: bool,
+    backup: bool,
+    backup_dir: Option<&'a Path>,
+}
 
 fn default_theme_option() -> String {
     "dark".to_string()
@@ -41,10 +158,26 @@ fn default_variant_option() -> String {
     "tonal_spot".to_string()
 }
 
+fn default_contrast_option() -> f64 {
+    0.0
+}
+
 fn default_recursive_option() -> bool {
     false
 }
 
+fn default_mode_option() -> Mode {
+    Mode::Link
+}
+
+fn default_relative_option() -> bool {
+    false
+}
+
+fn default_backup_option() -> bool {
+    false
+}
+
 impl TryFrom<&Path> for Manifest {
     type Error = error::Error;
     fn try_from(value: &Path) -> std::result::Result<Self, Self::Error> {
@@ -60,15 +193,160 @@ impl TryFrom<&Path> for Manifest {
                 parent_dir.display()
             )
         })?;
-        let manifest: Manifest = toml::from_str(
+        let mut manifest: Manifest = toml::from_str(
             &fs::read_to_string(&path)
                 .map_err(|err| format!("could not read file {}: {err}", path.display()))?,
         )
         .map_err(|err| format!("could not parse toml {}: {err}", path.display()))?;
+        manifest.root = parent_dir.to_path_buf();
         Ok(manifest)
     }
 }
 
+impl File {
+    fn is_enabled(&self) -> bool {
+        self.when.as_ref().is_none_or(|when| when.is_met())
+    }
+
+    /// Overrides the global options with the ones set on this entry.
+    fn options<'a>(&self, opts: Options<'a>) -> Options<'a> {
+        Options {
+            relative: self.relative.unwrap_or(opts.relative),
+            ..opts
+        }
+    }
+
+    fn apply_patch(&mut self, patch: FilePatch) {
+        if let Some(dest) = patch.dest {
+            self.dest = dest;
+        }
+        if patch.target.is_some() {
+            self.target = patch.target;
+        }
+        if patch.template.is_some() {
+            self.template = patch.template;
+        }
+        if let Some(recursive) = patch.recursive {
+            self.recursive = recursive;
+        }
+        if let Some(mode) = patch.mode {
+            self.mode = mode;
+        }
+        if patch.relative.is_some() {
+            self.relative = patch.relative;
+        }
+        if patch.palette.is_some() {
+            self.palette = patch.palette;
+        }
+        if patch.when.is_some() {
+            self.when = patch.when;
+        }
+        if let Some(hooks) = patch.hooks {
+            if hooks.post_link.is_some() {
+                self.hooks.post_link = hooks.post_link;
+            }
+            if hooks.post_generate.is_some() {
+                self.hooks.post_generate = hooks.post_generate;
+            }
+        }
+    }
+}
+
+impl TryFrom<FilePatch> for File {
+    type Error = error::Error;
+
+    fn try_from(patch: FilePatch) -> error::Result<Self> {
+        Ok(File {
+            target: patch.target,
+            dest: patch.dest.ok_or("dest is not set")?,
+            template: patch.template,
+            recursive: patch.recursive.unwrap_or_else(default_recursive_option),
+            mode: patch.mode.unwrap_or_else(default_mode_option),
+            relative: patch.relative,
+            palette: patch.palette,
+            when: patch.when,
+            hooks: patch.hooks.unwrap_or_default(),
+        })
+    }
+}
+
+impl Manifest {
+    /// Applies `[hosts.<hostname>]` and then `[profiles.<profile>]` on top of the base manifest.
+    fn apply_overlays(&mut self, profile: Option<&str>) -> error::Result<()> {
+        if let Some(hostname) = condition::hostname() {
+            if let Some(overlay) = self.hosts.shift_remove(&hostname) {
+                self.apply_overlay(overlay)
+                    .map_err(|err| format!("invalid hosts.{hostname}: {err}"))?;
+            }
+        }
+        if let Some(profile) = profile {
+            let overlay = self
+                .profiles
+                .shift_remove(profile)
+                .ok_or(format!("could not find profile {profile}"))?;
+            self.apply_overlay(overlay)
+                .map_err(|err| format!("invalid profiles.{profile}: {err}"))?;
+        }
+        Ok(())
+    }
+
+    /// `--contrast` wins over the contrast of the Manifest and of every named palette.
+    fn apply_contrast(&mut self, contrast: Option<f64>) {
+        if let Some(contrast) = contrast {
+            self.contrast = contrast;
+            for (_, palette) in self.palettes.iter_mut() {
+                palette.contrast = Some(contrast);
+            }
+        }
+    }
+
+    /// Applies the wallpaper set by `dotcraft wallpaper`, which takes precedence over every other
+    /// color source.
+    fn apply_state(&mut self, lockfile: &Lockfile) {
+        if let Some(wallpaper) = &lockfile.wallpaper {
+            self.wallpaper = Some(wallpaper.clone());
+            self.source_color = None;
+            self.scheme = None;
+        }
+    }
+
+    fn apply_overlay(&mut self, overlay: Overlay) -> error::Result<()> {
+        if overlay.wallpaper.is_some() || overlay.source_color.is_some() || overlay.scheme.is_some()
+        {
+            self.wallpaper = overlay.wallpaper;
+            self.source_color = overlay.source_color;
+            self.scheme = overlay.scheme;
+        }
+        if let Some(theme) = overlay.theme {
+            self.theme = theme;
+        }
+        if let Some(variant) = overlay.variant {
+            self.variant = variant;
+        }
+        if let Some(contrast) = overlay.contrast {
+            self.contrast = contrast;
+        }
+        for (name, file) in overlay.files {
+            match file {
+                FileOverlay::Enabled(true) => {}
+                FileOverlay::Enabled(false) => {
+                    self.files.shift_remove(&name);
+                }
+                FileOverlay::Patch(patch) => {
+                    if let Some(file) = self.files.get_mut(&name) {
+                        file.apply_patch(*patch);
+                    } else {
+                        let file = File::try_from(*patch)
+                            .map_err(|err| format!("could not add {name}: {err}"))?;
+                        self.files.insert(name, file);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 enum LogLevel {
     Info,
     Warning,
@@ -103,39 +381,151 @@ fn main() {
 
 fn entrypoint() -> error::Result<()> {
     let args = cli::Cli::try_parse()?;
+    #[cfg(target_os = "linux")]
+    if let cli::SubCommand::Watch { link } = args.subcommand {
+        return exec_watch_command(&args, link);
+    }
+    run(args)
+}
 
+fn run(args: cli::Cli) -> error::Result<()> {
     let mut config: VarMap = HashMap::new();
-    let manifest = Manifest::try_from(args.manifest_path.as_path())?;
+    let mut manifest = Manifest::try_from(args.manifest_path.as_path())?;
+    manifest.apply_overlays(args.profile.as_deref())?;
+    manifest.apply_contrast(args.contrast);
 
     let mut template_engine = upon::Engine::new();
     template_engine.add_filter("is_equal", |s: &str, other: &str| -> bool { s == other });
+    filters::register(&mut template_engine);
+
+    let mut lockfile = Lockfile::load(manifest.root.join("Manifest.lock"))?;
+    if let cli::SubCommand::Wallpaper { image, .. } = &args.subcommand {
+        // Only persisted once it decodes, so a bad file never ends up in the lockfile.
+        if let Some(image) = image {
+            colors::open_image(image)?;
+        }
+        lockfile.set_wallpaper(image.clone());
+    }
+    manifest.apply_state(&lockfile);
+
+    let backup_dir = if let Some(backup_dir) = &manifest.backup_dir {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|err| format!("could not read system time: {err}"))?
+            .as_secs();
+        Some(resolve_home_dir(backup_dir)?.join(timestamp.to_string()))
+    } else {
+        None
+    };
 
-    match args.subcommand {
-        cli::SubCommand::Sync { force, name } => {
-            exec_symlink_command(&name, force, &manifest.files)?;
-            exec_generate_command(&name, &manifest, &mut config, &mut template_engine)?;
+    let mut opts = Options {
+        dry_run: args.dry_run,
+        relative: manifest.relative,
+        backup: manifest.backup,
+        backup_dir: backup_dir.as_deref(),
+        ..Default::default()
+    };
+
+    let result = match args.subcommand {
+        cli::SubCommand::Sync {
+            force,
+            backup,
+            name,
+        } => {
+            opts.force = force;
+            opts.backup |= backup;
+            run_sync_hooks(&manifest.hooks, opts, || {
+                let linked = exec_symlink_command(&name, opts, &manifest.files, &mut lockfile)?;
+                let generated = exec_generate_command(
+                    &name,
+                    opts,
+                    &manifest,
+                    &mut config,
+                    &mut template_engine,
+                    &mut lockfile,
+                )?;
+                Ok(linked || generated)
+            })
+        }
+        cli::SubCommand::Link {
+            force,
+            backup,
+            name,
+        } => {
+            opts.force = force;
+            opts.backup |= backup;
+            run_sync_hooks(&manifest.hooks, opts, || {
+                exec_symlink_command(&name, opts, &manifest.files, &mut lockfile)
+            })
         }
-        cli::SubCommand::Link { force, name } => {
-            exec_symlink_command(&name, force, &manifest.files)?;
+        cli::SubCommand::Generate {
+            force,
+            backup,
+            name,
+        } => {
+            opts.force = force;
+            opts.backup |= backup;
+            run_sync_hooks(&manifest.hooks, opts, || {
+                exec_generate_command(
+                    &name,
+                    opts,
+                    &manifest,
+                    &mut config,
+                    &mut template_engine,
+                    &mut lockfile,
+                )
+            })
+        }
+        cli::SubCommand::Clean { name } => {
+            exec_clean_command(&name, opts, &manifest, &mut lockfile)
         }
-        cli::SubCommand::Generate { name } => {
-            exec_generate_command(&name, &manifest, &mut config, &mut template_engine)?;
+        cli::SubCommand::Restore { name } => exec_restore_command(&name, opts, &mut lockfile),
+        cli::SubCommand::Status { name } => exec_status_command(
+            &name,
+            &manifest,
+            &mut config,
+            &mut template_engine,
+            &lockfile,
+        ),
+        cli::SubCommand::Colors { format } => {
+            exec_colors_command(&format, opts, &manifest, &mut config)
+        }
+        #[cfg(target_os = "linux")]
+        cli::SubCommand::Watch { .. } => unreachable!("watch is handled by the entrypoint"),
+        cli::SubCommand::Wallpaper { force, backup, .. } => {
+            opts.force = force;
+            opts.backup |= backup;
+            run_sync_hooks(&manifest.hooks, opts, || {
+                exec_generate_command(
+                    &None,
+                    opts,
+                    &manifest,
+                    &mut config,
+                    &mut template_engine,
+                    &mut lockfile,
+                )
+            })
         }
+    };
+    if !opts.dry_run {
+        lockfile.save()?;
     }
-    Ok(())
+    result
 }
 
 fn exec_symlink_command(
     name: &Option<String>,
-    force: bool,
+    opts: Options,
     files: &IndexMap<String, File>,
-) -> error::Result<()> {
+    lockfile: &mut Lockfile,
+) -> error::Result<bool> {
+    let mut changed = false;
     if let Some(name) = name {
         if let Some(file) = files.get(name) {
-            if let Some(target) = &file.target {
-                symlink_dir_all(target, &file.dest, force, file.recursive).map_err(|err| {
-                    format!("something went wrong while symlinking {name}:\n    {err}")
-                })?;
+            if !file.is_enabled() {
+                log!(Info, "Skipped {name}. Condition not met.");
+            } else if let Some(target) = &file.target {
+                changed |= symlink_file_entry(name, file, target, opts, lockfile)?;
             }
         } else {
             return Err(format!("could not find {}", &name).into());
@@ -143,175 +533,910 @@ fn exec_symlink_command(
     } else {
         for (name, file) in files.iter() {
             if let Some(target) = &file.target {
-                symlink_dir_all(target, &file.dest, force, file.recursive).map_err(|err| {
-                    format!("something went wrong while symlinking {name}:\n    {err}")
-                })?;
+                if !file.is_enabled() {
+                    log!(Info, "Skipped symlinking {name}. Condition not met.");
+                    continue;
+                }
+                changed |= symlink_file_entry(name, file, target, opts, lockfile)?;
+            }
+        }
+        warn_orphans(lockfile, lockfile::Kind::Link, files);
+        warn_orphans(lockfile, lockfile::Kind::Copy, files);
+    }
+    Ok(changed)
+}
+
+fn symlink_file_entry(
+    name: &str,
+    file: &File,
+    target: &Path,
+    opts: Options,
+    lockfile: &mut Lockfile,
+) -> error::Result<bool> {
+    let changed = symlink_dir_all(
+        target,
+        &file.dest,
+        file.recursive,
+        file.mode,
+        name,
+        file.options(opts),
+        lockfile,
+    )
+    .map_err(|err| format!("something went wrong while symlinking {name}:\n    {err}"))?;
+    if changed {
+        if let Some(hook) = &file.hooks.post_link {
+            run_hook(hook, opts).map_err(|err| {
+                format!("something went wrong while running post_link hook of {name}:\n    {err}")
+            })?;
+        }
+    }
+    Ok(changed)
+}
+
+fn exec_clean_command(
+    name: &Option<String>,
+    opts: Options,
+    manifest: &Manifest,
+    lockfile: &mut Lockfile,
+) -> error::Result<()> {
+    if let Some(name) = name {
+        if let Some(file) = manifest.files.get(name) {
+            if let Some(target) = &file.target {
+                unlink_dir_all(
+                    target,
+                    &file.dest,
+                    file.recursive,
+                    file.mode,
+                    &manifest.root,
+                    opts,
+                    lockfile,
+                )
+                .map_err(|err| format!("something went wrong while cleaning {name}:\n    {err}"))?;
+            }
+        } else {
+            return Err(format!("could not find {}", &name).into());
+        }
+    } else {
+        for (name, file) in manifest.files.iter() {
+            if let Some(target) = &file.target {
+                unlink_dir_all(
+                    target,
+                    &file.dest,
+                    file.recursive,
+                    file.mode,
+                    &manifest.root,
+                    opts,
+                    lockfile,
+                )
+                .map_err(|err| format!("something went wrong while cleaning {name}:\n    {err}"))?;
             }
         }
+        let exists = |name: &str| manifest.files.contains_key(name);
+        let mut orphans = lockfile.orphans(lockfile::Kind::Link, exists);
+        orphans.extend(lockfile.orphans(lockfile::Kind::Copy, exists));
+        for (dest, entry) in orphans {
+            unlink_file(&dest, &manifest.root, opts, lockfile).map_err(|err| {
+                format!(
+                    "something went wrong while cleaning {}:\n    {err}",
+                    entry.name
+                )
+            })?;
+        }
+    }
+    Ok(())
+}
+
+fn exec_restore_command(
+    name: &Option<String>,
+    opts: Options,
+    lockfile: &mut Lockfile,
+) -> error::Result<()> {
+    let backups: Vec<(PathBuf, lockfile::Entry)> = lockfile
+        .entries
+        .iter()
+        .filter(|(_, entry)| name.as_ref().is_none_or(|name| &entry.name == name))
+        .filter(|(_, entry)| entry.backup.is_some())
+        .map(|(dest, entry)| (dest.clone(), entry.clone()))
+        .collect();
+    if let Some(name) = name {
+        if backups.is_empty() {
+            return Err(format!("could not find backups of {name}").into());
+        }
+    }
+    for (dest, entry) in backups {
+        restore_file(&dest, &entry, opts, lockfile)?;
     }
     Ok(())
 }
 
 fn exec_generate_command(
     name: &Option<String>,
+    opts: Options,
     manifest: &Manifest,
     config: &mut VarMap,
     template_engine: &mut upon::Engine,
-) -> error::Result<()> {
+    lockfile: &mut Lockfile,
+) -> error::Result<bool> {
+    let mut changed = false;
     if let Some(name) = name {
         if let Some(file) = manifest.files.get(name) {
-            if let Some(template) = &file.template {
-                create_color_palette(&manifest.wallpaper, config, manifest)?;
-                generate_template(&file.dest, template, config, template_engine).map_err(
-                    |err| format!("something went wrong while generating {name}:\n    {err}"),
+            if !file.is_enabled() {
+                log!(Info, "Skipped {name}. Condition not met.");
+            } else if let Some(template) = &file.template {
+                if file.palette.is_none() {
+                    create_color_palette(config, manifest, !opts.dry_run)?;
+                }
+                let mut palettes = HashMap::new();
+                let config = select_palette(file, manifest, config, &mut palettes, !opts.dry_run)?;
+                changed |= generate_file_entry(
+                    name,
+                    file,
+                    template,
+                    config,
+                    template_engine,
+                    opts,
+                    lockfile,
                 )?;
             }
         } else {
             return Err(format!("could not find {}", &name).into());
         }
     } else {
-        create_color_palette(&manifest.wallpaper, config, manifest)?;
+        create_color_palette(config, manifest, !opts.dry_run)?;
+        let mut palettes = HashMap::new();
         for (name, file) in manifest.files.iter() {
             if let Some(template) = &file.template {
-                generate_template(&file.dest, template, config, template_engine).map_err(
-                    |err| format!("something went wrong while generating {name}:\n    {err}"),
+                if !file.is_enabled() {
+                    log!(Info, "Skipped generating {name}. Condition not met.");
+                    continue;
+                }
+                let config = select_palette(file, manifest, config, &mut palettes, !opts.dry_run)?;
+                changed |= generate_file_entry(
+                    name,
+                    file,
+                    template,
+                    config,
+                    template_engine,
+                    opts,
+                    lockfile,
                 )?;
             }
         }
+        warn_orphans(lockfile, lockfile::Kind::Generated, &manifest.files);
+    }
+    Ok(changed)
+}
+
+fn generate_file_entry(
+    name: &str,
+    file: &File,
+    template: &Path,
+    config: &VarMap,
+    template_engine: &mut upon::Engine,
+    opts: Options,
+    lockfile: &mut Lockfile,
+) -> error::Result<bool> {
+    let changed = generate_template(
+        &file.dest,
+        template,
+        config,
+        template_engine,
+        name,
+        opts,
+        lockfile,
+    )
+    .map_err(|err| format!("something went wrong while generating {name}:\n    {err}"))?;
+    if changed {
+        if let Some(hook) = &file.hooks.post_generate {
+            run_hook(hook, opts).map_err(|err| {
+                format!(
+                    "something went wrong while running post_generate hook of {name}:\n    {err}"
+                )
+            })?;
+        }
+    }
+    Ok(changed)
+}
+
+fn run_sync_hooks(
+    hooks: &Hooks,
+    opts: Options,
+    sync: impl FnOnce() -> error::Result<bool>,
+) -> error::Result<()> {
+    if let Some(hook) = &hooks.pre_sync {
+        run_hook(hook, opts).map_err(|err| {
+            format!("something went wrong while running pre_sync hook:\n    {err}")
+        })?;
+    }
+    if sync()? {
+        if let Some(hook) = &hooks.post_sync {
+            run_hook(hook, opts).map_err(|err| {
+                format!("something went wrong while running post_sync hook:\n    {err}")
+            })?;
+        }
     }
     Ok(())
 }
 
-fn create_color_palette(
-    path: &Option<PathBuf>,
-    config: &mut VarMap,
+fn run_hook(hook: &str, opts: Options) -> error::Result<()> {
+    if opts.dry_run {
+        log!(Info, "Would run hook `{hook}`");
+        return Ok(());
+    }
+    log!(Info, "Running hook `{hook}`");
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(hook)
+        .status()
+        .map_err(|err| format!("could not run hook `{hook}`: {err}"))?;
+    if !status.success() {
+        return Err(format!("hook `{hook}` failed: {status}").into());
+    }
+    Ok(())
+}
+
+fn exec_status_command(
+    name: &Option<String>,
     manifest: &Manifest,
+    config: &mut VarMap,
+    template_engine: &mut upon::Engine,
+    lockfile: &Lockfile,
 ) -> error::Result<()> {
-    if let Some(wallpaper) = path {
-        let wp_path = wallpaper
-            .canonicalize()
-            .map_err(|err| format!("could not find {}: {err}", wallpaper.display()))?;
-        config.insert("wallpaper".to_string(), wp_path.display().to_string());
-        colors::generate_material_colors(&wp_path, &manifest.theme, &manifest.variant, config)?;
-    } else if has_templates(manifest) {
-        return Err("could not generate color palette: wallpaper is not set.".into());
+    let mut report: Vec<(&str, PathBuf, Status)> = Vec::new();
+    if let Some(name) = name {
+        if let Some(file) = manifest.files.get(name) {
+            if file.template.is_some() && file.palette.is_none() && file.is_enabled() {
+                create_color_palette(config, manifest, false)?;
+            }
+            let mut palettes = HashMap::new();
+            let config = if file.template.is_some() && file.is_enabled() {
+                select_palette(file, manifest, config, &mut palettes, false)?
+            } else {
+                &*config
+            };
+            status_entry(name, file, config, template_engine, lockfile, &mut report)
+                .map_err(|err| format!("something went wrong while checking {name}:\n    {err}"))?;
+        } else {
+            return Err(format!("could not find {}", &name).into());
+        }
     } else {
-        log!(Warning, "Skipping color scheme generation.");
+        if has_templates(manifest) {
+            create_color_palette(config, manifest, false)?;
+        }
+        let mut palettes = HashMap::new();
+        for (name, file) in manifest.files.iter() {
+            let config = if file.template.is_some() && file.is_enabled() {
+                select_palette(file, manifest, config, &mut palettes, false)?
+            } else {
+                &*config
+            };
+            status_entry(name, file, config, template_engine, lockfile, &mut report)
+                .map_err(|err| format!("something went wrong while checking {name}:\n    {err}"))?;
+        }
+        for (dest, entry) in lockfile.entries.iter() {
+            if !manifest.files.contains_key(&entry.name) {
+                report.push((&entry.name, dest.clone(), Status::Orphaned));
+            }
+        }
     }
-    Ok(())
-}
 
-fn has_templates(manifest: &Manifest) -> bool {
-    for (_, file) in manifest.files.iter() {
-        if file.template.is_some() {
-            return true;
+    let mut out_of_sync = 0;
+    for (name, dest, status) in report.iter() {
+        if *status != Status::UpToDate {
+            out_of_sync += 1;
+        }
+        if let Status::Elsewhere(origin) = status {
+            println!(
+                "{status} {name}: {} -> {}",
+                dest.display(),
+                origin.display()
+            );
+        } else {
+            println!("{status} {name}: {}", dest.display());
         }
     }
-    false
+    if out_of_sync > 0 {
+        return Err(format!("{out_of_sync} destination(s) out of sync").into());
+    }
+    Ok(())
 }
 
-fn resolve_home_dir(path: impl AsRef<Path>) -> error::Result<PathBuf> {
-    let path = path.as_ref();
-    let home_dir =
-        std::env::var("HOME").map_err(|err| format!("could not find home directory: {err}"))?;
+#[derive(Debug, PartialEq)]
+enum Status {
+    UpToDate,
+    Missing,
+    Broken,
+    Elsewhere(PathBuf),
+    Blocked,
+    Stale,
+    /// Generated file that was edited by hand since it was generated.
+    Modified,
+    /// Recorded in the lockfile under a name that is no longer in the manifest.
+    Orphaned,
+}
 
-    if let Some(prefix) = path.components().next() {
-        if prefix == Component::Normal("~".as_ref()) {
-            if let Ok(stripped_path) = path.strip_prefix("~") {
-                let mut result = PathBuf::new();
-                result.push(home_dir);
-                result.push(stripped_path);
-                return Ok(result);
+/// Reruns `generate` (or `sync` with `link`) every time the manifest or one of its sources changes.
+#[cfg(target_os = "linux")]
+fn exec_watch_command(args: &cli::Cli, link: bool) -> error::Result<()> {
+    let manifest_path = args
+        .manifest_path
+        .canonicalize()
+        .map_err(|err| format!("invalid path {}: {err}", args.manifest_path.display()))?;
+    loop {
+        // Watch before running so that changes made during the run are not missed.
+        let mut watcher = watch::Watcher::new()?;
+        watcher.add_file(&manifest_path)?;
+        let loaded = match Manifest::try_from(manifest_path.as_path()) {
+            Ok(mut manifest) => {
+                // Overlay errors are reported by the run below.
+                let _ = manifest.apply_overlays(args.profile.as_deref());
+                if let Ok(lockfile) = Lockfile::load(manifest.root.join("Manifest.lock")) {
+                    manifest.apply_state(&lockfile);
+                }
+                watch_sources(&mut watcher, &manifest, link);
+                true
+            }
+            Err(err) => {
+                log!(Error, "{err}");
+                false
+            }
+        };
+
+        let subcommand = if link {
+            cli::SubCommand::Sync {
+                force: false,
+                backup: false,
+                name: None,
+            }
+        } else {
+            cli::SubCommand::Generate {
+                force: false,
+                backup: false,
+                name: None,
+            }
+        };
+        if loaded {
+            if let Err(err) = run(cli::Cli {
+                manifest_path: manifest_path.clone(),
+                profile: args.profile.clone(),
+                contrast: args.contrast,
+                dry_run: args.dry_run,
+                subcommand,
+            }) {
+                log!(Error, "{err}");
             }
         }
+
+        log!(Info, "Watching {} path(s) for changes.", watcher.len());
+        watcher.wait()?;
     }
-    Ok(path.to_path_buf())
 }
 
-fn symlink_dir_all(
-    target: impl AsRef<Path>,
-    dest: impl AsRef<Path>,
-    force: bool,
-    recursive: bool,
-) -> error::Result<()> {
-    let target = resolve_home_dir(&target)?
-        .canonicalize()
-        .map_err(|err| format!("could not find {}: {err}", target.as_ref().display()))?;
-    let dest = resolve_home_dir(dest)?;
+/// Paths that fail to be watched are logged and skipped, so one missing file does not stop the
+/// rest from being watched.
+#[cfg(target_os = "linux")]
+fn watch_sources(watcher: &mut watch::Watcher, manifest: &Manifest, link: bool) {
+    let mut paths: Vec<&PathBuf> = Vec::new();
+    paths.extend(&manifest.wallpaper);
+    paths.extend(&manifest.scheme);
+    for (_, palette) in manifest.palettes.iter() {
+        paths.extend(&palette.wallpaper);
+        paths.extend(&palette.scheme);
+    }
+    for (_, file) in manifest.files.iter() {
+        if !file.is_enabled() {
+            continue;
+        }
+        paths.extend(&file.template);
+        if link {
+            paths.extend(&file.target);
+        }
+    }
 
-    if target.is_dir() && recursive {
-        for entry in fs::read_dir(target)? {
-            let entry = entry?;
-            let dest = &dest.join(entry.path().file_name().ok_or(format!(
-                "could not extract file_name of {}",
-                entry.path().display()
-            ))?);
-            let dest_parent_dir = dest
-                .parent()
-                .ok_or(format!("could not access parent dir of {}", dest.display()))?;
-            if !dest_parent_dir.exists() {
-                fs::create_dir_all(dest_parent_dir).map_err(|err| {
-                    format!("could not create dir {}: {err}", dest_parent_dir.display())
-                })?;
+    for path in paths {
+        let result = resolve_home_dir(path).and_then(|path| {
+            if path.is_dir() {
+                watcher.add_dir_all(&path)
+            } else {
+                watcher.add_file(&path)
             }
-            symlink_dir_all(entry.path(), dest, force, recursive)?;
+        });
+        if let Err(err) = result {
+            log!(Error, "{err}");
         }
-    } else {
-        symlink_file(&target, &dest, force)?;
     }
-    Ok(())
 }
 
-fn symlink_file(
-    target: impl AsRef<Path>,
-    dest: impl AsRef<Path>,
-    force: bool,
+fn exec_colors_command(
+    format: &Option<String>,
+    opts: Options,
+    manifest: &Manifest,
+    config: &mut VarMap,
 ) -> error::Result<()> {
-    let target = target.as_ref();
-    let dest = dest.as_ref();
+    if manifest.wallpaper.is_none() && manifest.source_color.is_none() && manifest.scheme.is_none()
+    {
+        return Err(
+            "could not generate color palette: none of wallpaper, source_color or scheme is set."
+                .into(),
+        );
+    }
+    create_color_palette(config, manifest, !opts.dry_run)?;
+    if let Some(format) = format {
+        print!("{}", export::export(config, format)?);
+        return Ok(());
+    }
 
-    match symlink(target, dest) {
-        Ok(()) => {
-            log!(Info, "Symlinked {} to {}", target.display(), dest.display());
+    let mut info: Vec<(&String, &String)> = Vec::new();
+    let mut roles: Vec<(&String, &String)> = Vec::new();
+    let mut base16: Vec<(&String, &String)> = Vec::new();
+    for (key, value) in config.iter() {
+        let upon::Value::String(value) = value else {
+            continue;
+        };
+        // `base0`..`base15` are only aliases of `base00`..`base0F`.
+        let is_base16 = key.len() == 6 && key.starts_with("base0");
+        if key.contains("_tone_") || (key.starts_with("base") && !is_base16) {
+            continue;
+        } else if colors::parse_color(value).is_err() {
+            info.push((key, value));
+        } else if key.starts_with("base") {
+            base16.push((key, value));
+        } else {
+            roles.push((key, value));
         }
-        Err(err) => match err.kind() {
-            io::ErrorKind::AlreadyExists => {
-                if force {
-                    log!(
-                        Warning,
-                        "Destination {} already exists. Removing",
+    }
+    info.sort();
+    roles.sort();
+    base16.sort();
+
+    for (key, value) in info {
+        println!("{key}: {value}");
+    }
+    if !roles.is_empty() {
+        println!("\n\x1b[1mMaterial roles\x1b[0m");
+        for (key, value) in roles {
+            println!("{} {key:<28} {value}", swatch(value));
+        }
+    }
+    let palettes = [
+        "primary",
+        "secondary",
+        "tertiary",
+        "neutral",
+        "neutral_variant",
+        "error",
+    ];
+    if config.contains_key("primary_tone_0") {
+        println!("\n\x1b[1mTonal palettes\x1b[0m (0, 10, ..., 100)");
+        for palette in palettes {
+            let tones: Vec<String> = (0..=100)
+                .step_by(10)
+                .filter_map(|tone| match config.get(&format!("{palette}_tone_{tone}")) {
+                    Some(upon::Value::String(value)) => Some(swatch(value)),
+                    _ => None,
+                })
+                .collect();
+            println!("{palette:<16} {}", tones.join(""));
+        }
+    }
+    if !base16.is_empty() {
+        println!("\n\x1b[1mBase16\x1b[0m");
+        for (key, value) in base16 {
+            println!("{} {key:<28} {value}", swatch(value));
+        }
+    }
+    Ok(())
+}
+
+/// Returns a truecolor block of `color`, or blank space if it is not a valid color.
+fn swatch(color: &str) -> String {
+    match colors::parse_color(color) {
+        Ok(argb) => format!(
+            "\x1b[48;2;{};{};{}m    \x1b[0m",
+            argb.red, argb.green, argb.blue
+        ),
+        Err(_) => "    ".to_string(),
+    }
+}
+
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Status::UpToDate => write!(f, "\x1b[0;32m{:<10}\x1b[0m", "ok"),
+            Status::Missing => write!(f, "\x1b[0;33m{:<10}\x1b[0m", "missing"),
+            Status::Broken => write!(f, "\x1b[0;31m{:<10}\x1b[0m", "broken"),
+            Status::Elsewhere(_) => write!(f, "\x1b[0;31m{:<10}\x1b[0m", "elsewhere"),
+            Status::Blocked => write!(f, "\x1b[0;31m{:<10}\x1b[0m", "blocked"),
+            Status::Stale => write!(f, "\x1b[0;33m{:<10}\x1b[0m", "stale"),
+            Status::Modified => write!(f, "\x1b[0;31m{:<10}\x1b[0m", "modified"),
+            Status::Orphaned => write!(f, "\x1b[0;33m{:<10}\x1b[0m", "orphaned"),
+        }
+    }
+}
+
+fn status_entry<'a>(
+    name: &'a str,
+    file: &File,
+    config: &VarMap,
+    template_engine: &mut upon::Engine,
+    lockfile: &Lockfile,
+    report: &mut Vec<(&'a str, PathBuf, Status)>,
+) -> error::Result<()> {
+    if !file.is_enabled() {
+        return Ok(());
+    }
+    if let Some(target) = &file.target {
+        status_dir_all(name, target, &file.dest, file.recursive, file.mode, report)?;
+    }
+    if let Some(template) = &file.template {
+        let (_, rendered) = render_template(template, config, template_engine)?;
+        let dest = resolve_home_dir(&file.dest)?;
+        let current = fs::read(&dest).ok();
+        let modified = match (&current, lockfile.get(&dest)) {
+            (Some(current), Some(generated)) if generated.kind == lockfile::Kind::Generated => {
+                lockfile::hash_bytes(current) != generated.hash
+            }
+            _ => false,
+        };
+        let status = if dest.is_symlink() && !dest.exists() {
+            Status::Broken
+        } else if !dest.exists() {
+            Status::Missing
+        } else if current.as_deref() == Some(rendered.as_bytes()) {
+            Status::UpToDate
+        } else if modified {
+            Status::Modified
+        } else {
+            Status::Stale
+        };
+        report.push((name, dest, status));
+    }
+    Ok(())
+}
+
+fn status_dir_all<'a>(
+    name: &'a str,
+    target: impl AsRef<Path>,
+    dest: impl AsRef<Path>,
+    recursive: bool,
+    mode: Mode,
+    report: &mut Vec<(&'a str, PathBuf, Status)>,
+) -> error::Result<()> {
+    let target = resolve_home_dir(&target)?
+        .canonicalize()
+        .map_err(|err| format!("could not find {}: {err}", target.as_ref().display()))?;
+    let dest = resolve_home_dir(dest)?;
+
+    if target.is_dir() && (recursive || mode == Mode::Copy) {
+        for entry in fs::read_dir(target)? {
+            let entry = entry?;
+            let dest = dest.join(entry.path().file_name().ok_or(format!(
+                "could not extract file_name of {}",
+                entry.path().display()
+            ))?);
+            status_dir_all(name, entry.path(), dest, recursive, mode, report)?;
+        }
+    } else if mode == Mode::Copy {
+        let status = copy_status(&target, &dest)?;
+        report.push((name, dest, status));
+    } else {
+        let status = link_status(&target, &dest)?;
+        report.push((name, dest, status));
+    }
+    Ok(())
+}
+
+fn link_status(target: &Path, dest: &Path) -> error::Result<Status> {
+    if dest.is_symlink() {
+        if !dest.exists() {
+            Ok(Status::Broken)
+        } else {
+            let symlink_origin = dest.canonicalize()?;
+            if target.canonicalize()? == symlink_origin {
+                Ok(Status::UpToDate)
+            } else {
+                Ok(Status::Elsewhere(symlink_origin))
+            }
+        }
+    } else if dest.exists() {
+        Ok(Status::Blocked)
+    } else {
+        Ok(Status::Missing)
+    }
+}
+
+fn copy_status(target: &Path, dest: &Path) -> error::Result<Status> {
+    if dest.is_symlink() {
+        Ok(Status::Blocked)
+    } else if !dest.exists() {
+        Ok(Status::Missing)
+    } else if dest.is_file() && lockfile::hash_path(dest)? == lockfile::hash_path(target)? {
+        Ok(Status::UpToDate)
+    } else {
+        Ok(Status::Stale)
+    }
+}
+
+fn warn_orphans(lockfile: &Lockfile, kind: lockfile::Kind, files: &IndexMap<String, File>) {
+    for (dest, entry) in lockfile.orphans(kind, |name| files.contains_key(name)) {
+        log!(
+            Warning,
+            "Destination {} belongs to {} which is no longer in the manifest.",
+            dest.display(),
+            entry.name
+        );
+    }
+}
+
+fn create_color_palette(
+    config: &mut VarMap,
+    manifest: &Manifest,
+    save_cache: bool,
+) -> error::Result<()> {
+    let palette = Palette {
+        wallpaper: manifest.wallpaper.clone(),
+        source_color: manifest.source_color.clone(),
+        scheme: manifest.scheme.clone(),
+        theme: None,
+        variant: None,
+        contrast: None,
+    };
+    if !generate_palette(config, &palette, manifest, save_cache)? {
+        if has_templates(manifest) {
+            return Err(
+                "could not generate color palette: none of wallpaper, source_color or scheme is set."
+                    .into(),
+            );
+        }
+        if manifest.palettes.is_empty() {
+            log!(Warning, "Skipping color scheme generation.");
+        }
+    }
+    Ok(())
+}
+
+/// Returns the palette `file` asks for. Named palettes are generated the first time they are used.
+fn select_palette<'a>(
+    file: &File,
+    manifest: &Manifest,
+    config: &'a VarMap,
+    palettes: &'a mut HashMap<String, VarMap>,
+    save_cache: bool,
+) -> error::Result<&'a VarMap> {
+    let Some(name) = &file.palette else {
+        return Ok(config);
+    };
+    if !palettes.contains_key(name) {
+        let palette = manifest
+            .palettes
+            .get(name)
+            .ok_or(format!("could not find palette {name}"))?;
+        let mut named = VarMap::new();
+        if !generate_palette(&mut named, palette, manifest, save_cache)? {
+            return Err(format!(
+                "could not generate palette {name}: none of wallpaper, source_color or scheme is set."
+            )
+            .into());
+        }
+        palettes.insert(name.clone(), named);
+    }
+    Ok(&palettes[name])
+}
+
+/// Returns false if the palette has no color source. `save_cache` is off for dry runs and `status`,
+/// which must not write anything.
+fn generate_palette(
+    config: &mut VarMap,
+    palette: &Palette,
+    manifest: &Manifest,
+    save_cache: bool,
+) -> error::Result<bool> {
+    let theme = palette.theme.as_ref().unwrap_or(&manifest.theme);
+    let variant = palette.variant.as_ref().unwrap_or(&manifest.variant);
+    let contrast = palette.contrast.unwrap_or(manifest.contrast);
+    if let Some(scheme) = &palette.scheme {
+        let scheme_path = scheme
+            .canonicalize()
+            .map_err(|err| format!("could not find {}: {err}", scheme.display()))?;
+        colors::load_base16_scheme(&scheme_path, config)?;
+    } else if let Some(wallpaper) = &palette.wallpaper {
+        let wp_path = wallpaper
+            .canonicalize()
+            .map_err(|err| format!("could not find {}: {err}", wallpaper.display()))?;
+        create_wallpaper_palette(&wp_path, theme, variant, contrast, config, save_cache)?;
+        config.insert(
+            "wallpaper".to_string(),
+            wp_path.display().to_string().into(),
+        );
+    } else if let Some(color) = &palette.source_color {
+        let source_color = colors::parse_color(color)?;
+        let theme = colors::resolve_theme(theme, source_color.as_lstar());
+        colors::generate_material_colors(source_color, theme, variant, contrast, config)?;
+    } else {
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Extracting the source color from an image is slow, so the palette is cached per wallpaper.
+fn create_wallpaper_palette(
+    wp_path: &Path,
+    theme: &str,
+    variant: &str,
+    contrast: f64,
+    config: &mut VarMap,
+    save_cache: bool,
+) -> error::Result<()> {
+    let cache_path = cache::palette_path(wp_path, theme, variant, contrast)?;
+    if let Some(cached) = cache::load_palette(&cache_path) {
+        config.extend(cached);
+        return Ok(());
+    }
+
+    let mut palette = VarMap::new();
+    let (source_color, lightness) = colors::extract_wallpaper_color(wp_path)?;
+    let theme = colors::resolve_theme(theme, lightness);
+    colors::generate_material_colors(source_color, theme, variant, contrast, &mut palette)?;
+    if save_cache {
+        if let Err(err) = cache::save_palette(&cache_path, &palette) {
+            log!(Warning, "Could not cache color palette: {err}");
+        }
+    }
+    config.extend(palette);
+    Ok(())
+}
+
+fn has_templates(manifest: &Manifest) -> bool {
+    for (_, file) in manifest.files.iter() {
+        if file.template.is_some() && file.palette.is_none() && file.is_enabled() {
+            return true;
+        }
+    }
+    false
+}
+
+fn resolve_home_dir(path: impl AsRef<Path>) -> error::Result<PathBuf> {
+    let path = path.as_ref();
+    let home_dir =
+        std::env::var("HOME").map_err(|err| format!("could not find home directory: {err}"))?;
+
+    if let Some(prefix) = path.components().next() {
+        if prefix == Component::Normal("~".as_ref()) {
+            if let Ok(stripped_path) = path.strip_prefix("~") {
+                let mut result = PathBuf::new();
+                result.push(home_dir);
+                result.push(stripped_path);
+                return Ok(result);
+            }
+        }
+    }
+    Ok(path.to_path_buf())
+}
+
+fn symlink_dir_all(
+    target: impl AsRef<Path>,
+    dest: impl AsRef<Path>,
+    recursive: bool,
+    mode: Mode,
+    name: &str,
+    opts: Options,
+    lockfile: &mut Lockfile,
+) -> error::Result<bool> {
+    let target = resolve_home_dir(&target)?
+        .canonicalize()
+        .map_err(|err| format!("could not find {}: {err}", target.as_ref().display()))?;
+    let dest = resolve_home_dir(dest)?;
+
+    let mut changed = false;
+    if target.is_dir() && (recursive || mode == Mode::Copy) {
+        let entries = fs::read_dir(target)?.collect::<io::Result<Vec<_>>>()?;
+        // Every entry shares `dest` as its parent, so it is created (or announced) only once.
+        if !entries.is_empty() && !dest.exists() {
+            if opts.dry_run {
+                log!(Info, "Would create dir {}", dest.display());
+            } else {
+                fs::create_dir_all(&dest)
+                    .map_err(|err| format!("could not create dir {}: {err}", dest.display()))?;
+            }
+        }
+        for entry in entries {
+            let dest = &dest.join(entry.path().file_name().ok_or(format!(
+                "could not extract file_name of {}",
+                entry.path().display()
+            ))?);
+            changed |= symlink_dir_all(entry.path(), dest, recursive, mode, name, opts, lockfile)?;
+        }
+    } else if mode == Mode::Copy {
+        changed = copy_file(&target, &dest, name, opts, lockfile)?;
+    } else {
+        changed = symlink_file(&target, &dest, name, opts, lockfile)?;
+    }
+    Ok(changed)
+}
+
+fn symlink_file(
+    target: impl AsRef<Path>,
+    dest: impl AsRef<Path>,
+    name: &str,
+    opts: Options,
+    lockfile: &mut Lockfile,
+) -> error::Result<bool> {
+    let target = target.as_ref();
+    let dest = dest.as_ref();
+
+    if opts.dry_run {
+        return preview_symlink_file(target, dest, opts);
+    }
+
+    let link = link_path(target, dest, opts.relative)?;
+    let mut changed = false;
+    match symlink(&link, dest) {
+        Ok(()) => {
+            log!(Info, "Symlinked {} to {}", target.display(), dest.display());
+            changed = true;
+            record_link(target, dest, name, None, lockfile)?;
+        }
+        Err(err) => match err.kind() {
+            io::ErrorKind::AlreadyExists => {
+                if dest.is_symlink()
+                    && dest.exists()
+                    && target.canonicalize()? == dest.canonicalize()?
+                {
+                    if fs::read_link(dest)? != link {
+                        fs::remove_file(dest).map_err(|err| {
+                            format!("could not remove file {}: {err}", dest.display())
+                        })?;
+                        symlink(&link, dest)?;
+                        log!(Info, "Symlinked {} to {}", target.display(), dest.display());
+                        changed = true;
+                    } else {
+                        log!(Info, "Skipped symlinking {}. Up to date.", dest.display());
+                    }
+                    record_link(target, dest, name, None, lockfile)?;
+                } else if dest.is_symlink() && !dest.exists() {
+                    log!(
+                        Warning,
+                        "Destination {} is a broken symlink. Ignoring",
                         dest.display()
                     );
                     std::fs::remove_file(dest).map_err(|err| {
                         format!("could not remove file {}: {err}", dest.display())
                     })?;
-                    symlink(target, dest)?;
+                    symlink(&link, dest)?;
+                    log!(Info, "Symlinked {} to {}", target.display(), dest.display());
+                    changed = true;
+                    record_link(target, dest, name, None, lockfile)?;
+                } else if opts.backup {
+                    let backup = backup_path(dest, opts)?;
+                    log!(
+                        Warning,
+                        "Destination {} already exists. Backing up to {}",
+                        dest.display(),
+                        backup.display()
+                    );
+                    move_path(dest, &backup)?;
+                    symlink(&link, dest)?;
+                    log!(Info, "Symlinked {} to {}", target.display(), dest.display());
+                    changed = true;
+                    record_link(target, dest, name, Some(backup), lockfile)?;
+                } else if opts.force {
+                    log!(
+                        Warning,
+                        "Destination {} already exists. Removing",
+                        dest.display()
+                    );
+                    remove_path(dest)?;
+                    symlink(&link, dest)?;
                     log!(Info, "Symlinked {} to {}", target.display(), dest.display());
+                    changed = true;
+                    record_link(target, dest, name, None, lockfile)?;
                 } else if dest.is_symlink() {
-                    if !dest.exists() {
-                        log!(
-                            Warning,
-                            "Destination {} is a broken symlink. Ignoring",
-                            dest.display()
-                        );
-                        std::fs::remove_file(dest).map_err(|err| {
-                            format!("could not remove file {}: {err}", dest.display())
-                        })?;
-                        symlink(target, dest)?;
-                        log!(Info, "Symlinked {} to {}", target.display(), dest.display());
-                    } else {
-                        let symlink_origin = dest.canonicalize()?;
-                        if target.canonicalize()? == symlink_origin {
-                            log!(Info, "Skipped symlinking {}. Up to date.", dest.display());
-                        } else {
-                            log!(
-                                Warning,
-                                "Destination {} is symlinked to {}. Resolve manually.",
-                                dest.display(),
-                                symlink_origin.display()
-                            );
-                        }
-                    }
+                    log!(
+                        Warning,
+                        "Destination {} is symlinked to {}. Resolve manually.",
+                        dest.display(),
+                        dest.canonicalize()?.display()
+                    );
                 } else {
                     log!(
                         Warning,
@@ -330,6 +1455,387 @@ fn symlink_file(
             }
         },
     }
+    Ok(changed)
+}
+
+fn link_path(target: &Path, dest: &Path, relative: bool) -> error::Result<PathBuf> {
+    if !relative {
+        return Ok(target.to_path_buf());
+    }
+    let dest_parent_dir = std::path::absolute(dest)
+        .map_err(|err| format!("invalid path {}: {err}", dest.display()))?
+        .parent()
+        .ok_or(format!("could not access parent dir of {}", dest.display()))?
+        .to_path_buf();
+    let dest_parent_dir = dest_parent_dir.canonicalize().unwrap_or(dest_parent_dir);
+
+    let mut target_components = target.components().peekable();
+    let mut parent_components = dest_parent_dir.components().peekable();
+    while let (Some(a), Some(b)) = (target_components.peek(), parent_components.peek()) {
+        if a != b {
+            break;
+        }
+        target_components.next();
+        parent_components.next();
+    }
+
+    let mut result = PathBuf::new();
+    for _ in parent_components {
+        result.push("..");
+    }
+    result.extend(target_components);
+    Ok(result)
+}
+
+fn copy_file(
+    target: impl AsRef<Path>,
+    dest: impl AsRef<Path>,
+    name: &str,
+    opts: Options,
+    lockfile: &mut Lockfile,
+) -> error::Result<bool> {
+    let target = target.as_ref();
+    let dest = dest.as_ref();
+
+    let source_hash = lockfile::hash_path(target)?;
+    let copied_hash = lockfile
+        .get(dest)
+        .filter(|entry| entry.kind == lockfile::Kind::Copy)
+        .map(|entry| entry.hash.clone());
+
+    if opts.dry_run {
+        return preview_copy_file(target, dest, copied_hash, opts);
+    }
+
+    if dest.symlink_metadata().is_err() {
+        copy_and_log(target, dest)?;
+        record_copy(target, dest, name, source_hash, None, lockfile)?;
+        Ok(true)
+    } else if !dest.is_symlink() && dest.is_file() {
+        let dest_hash = lockfile::hash_path(dest)?;
+        if dest_hash == source_hash {
+            log!(Info, "Skipped copying {}. Up to date.", dest.display());
+            record_copy(target, dest, name, source_hash, None, lockfile)?;
+            Ok(false)
+        } else if copied_hash.as_ref() == Some(&dest_hash) {
+            log!(Info, "Source {} changed. Copying again", target.display());
+            copy_and_log(target, dest)?;
+            record_copy(target, dest, name, source_hash, None, lockfile)?;
+            Ok(true)
+        } else {
+            replace_with_copy(target, dest, name, source_hash, opts, lockfile)
+        }
+    } else {
+        replace_with_copy(target, dest, name, source_hash, opts, lockfile)
+    }
+}
+
+fn replace_with_copy(
+    target: &Path,
+    dest: &Path,
+    name: &str,
+    source_hash: String,
+    opts: Options,
+    lockfile: &mut Lockfile,
+) -> error::Result<bool> {
+    if dest.is_symlink() && !dest.exists() {
+        log!(
+            Warning,
+            "Destination {} is a broken symlink. Ignoring",
+            dest.display()
+        );
+        remove_path(dest)?;
+        copy_and_log(target, dest)?;
+        record_copy(target, dest, name, source_hash, None, lockfile)?;
+        Ok(true)
+    } else if opts.backup {
+        let backup = backup_path(dest, opts)?;
+        log!(
+            Warning,
+            "Destination {} already exists. Backing up to {}",
+            dest.display(),
+            backup.display()
+        );
+        move_path(dest, &backup)?;
+        copy_and_log(target, dest)?;
+        record_copy(target, dest, name, source_hash, Some(backup), lockfile)?;
+        Ok(true)
+    } else if opts.force {
+        log!(
+            Warning,
+            "Destination {} already exists. Removing",
+            dest.display()
+        );
+        remove_path(dest)?;
+        copy_and_log(target, dest)?;
+        record_copy(target, dest, name, source_hash, None, lockfile)?;
+        Ok(true)
+    } else {
+        log!(
+            Warning,
+            "Destination {} differs from {}. Resolve manually",
+            dest.display(),
+            target.display()
+        );
+        Ok(false)
+    }
+}
+
+fn copy_and_log(target: &Path, dest: &Path) -> error::Result<()> {
+    fs::copy(target, dest).map_err(|err| {
+        format!(
+            "could not copy {} to {}: {err}",
+            target.display(),
+            dest.display()
+        )
+    })?;
+    log!(Info, "Copied {} to {}", target.display(), dest.display());
+    Ok(())
+}
+
+/// Returns whether the copy would change anything.
+fn preview_copy_file(
+    target: &Path,
+    dest: &Path,
+    copied_hash: Option<String>,
+    opts: Options,
+) -> error::Result<bool> {
+    let changed = match copy_status(target, dest)? {
+        Status::Missing => {
+            log!(
+                Info,
+                "Would copy {} to {}",
+                target.display(),
+                dest.display()
+            );
+            true
+        }
+        Status::UpToDate => {
+            log!(Info, "Skipped copying {}. Up to date.", dest.display());
+            false
+        }
+        Status::Stale if dest.is_file() && copied_hash == Some(lockfile::hash_path(dest)?) => {
+            log!(
+                Info,
+                "Source {} changed. Would copy again",
+                target.display()
+            );
+            true
+        }
+        _ if dest.is_symlink() && !dest.exists() => {
+            log!(
+                Warning,
+                "Destination {} is a broken symlink. Would remove",
+                dest.display()
+            );
+            log!(
+                Info,
+                "Would copy {} to {}",
+                target.display(),
+                dest.display()
+            );
+            true
+        }
+        _ if opts.backup => {
+            log!(
+                Warning,
+                "Destination {} already exists. Would back up to {}",
+                dest.display(),
+                backup_path(dest, opts)?.display()
+            );
+            log!(
+                Info,
+                "Would copy {} to {}",
+                target.display(),
+                dest.display()
+            );
+            true
+        }
+        _ if opts.force => {
+            log!(
+                Warning,
+                "Destination {} already exists. Would remove",
+                dest.display()
+            );
+            log!(
+                Info,
+                "Would copy {} to {}",
+                target.display(),
+                dest.display()
+            );
+            true
+        }
+        _ => {
+            log!(
+                Warning,
+                "Destination {} differs from {}. Resolve manually",
+                dest.display(),
+                target.display()
+            );
+            false
+        }
+    };
+    Ok(changed)
+}
+
+/// Returns whether the symlink would change anything.
+fn preview_symlink_file(target: &Path, dest: &Path, opts: Options) -> error::Result<bool> {
+    let status = link_status(target, dest)?;
+    if !matches!(status, Status::Missing | Status::UpToDate | Status::Broken) {
+        if opts.backup {
+            log!(
+                Warning,
+                "Destination {} already exists. Would back up to {}",
+                dest.display(),
+                backup_path(dest, opts)?.display()
+            );
+            log!(
+                Info,
+                "Would symlink {} to {}",
+                target.display(),
+                dest.display()
+            );
+            return Ok(true);
+        } else if opts.force {
+            log!(
+                Warning,
+                "Destination {} already exists. Would remove",
+                dest.display()
+            );
+            log!(
+                Info,
+                "Would symlink {} to {}",
+                target.display(),
+                dest.display()
+            );
+            return Ok(true);
+        }
+    }
+    let changed = match status {
+        Status::Missing => {
+            log!(
+                Info,
+                "Would symlink {} to {}",
+                target.display(),
+                dest.display()
+            );
+            true
+        }
+        Status::Broken => {
+            log!(
+                Warning,
+                "Destination {} is a broken symlink. Would remove",
+                dest.display()
+            );
+            log!(
+                Info,
+                "Would symlink {} to {}",
+                target.display(),
+                dest.display()
+            );
+            true
+        }
+        // The link is rewritten when `relative` changed.
+        Status::UpToDate if fs::read_link(dest)? != link_path(target, dest, opts.relative)? => {
+            log!(
+                Info,
+                "Would symlink {} to {}",
+                target.display(),
+                dest.display()
+            );
+            true
+        }
+        Status::UpToDate => {
+            log!(Info, "Skipped symlinking {}. Up to date.", dest.display());
+            false
+        }
+        Status::Elsewhere(symlink_origin) => {
+            log!(
+                Warning,
+                "Destination {} is symlinked to {}. Resolve manually.",
+                dest.display(),
+                symlink_origin.display()
+            );
+            false
+        }
+        Status::Blocked | Status::Stale | Status::Modified | Status::Orphaned => {
+            log!(
+                Warning,
+                "Destination {} exists but it's not a symlink. Resolve manually",
+                dest.display()
+            );
+            false
+        }
+    };
+    Ok(changed)
+}
+
+fn backup_path(dest: &Path, opts: Options) -> error::Result<PathBuf> {
+    let backup = if let Some(backup_dir) = opts.backup_dir {
+        let dest = std::path::absolute(dest)
+            .map_err(|err| format!("invalid path {}: {err}", dest.display()))?;
+        backup_dir.join(dest.strip_prefix("/").unwrap_or(&dest))
+    } else {
+        let mut backup = dest.as_os_str().to_owned();
+        backup.push(".dotcraft-bak");
+        PathBuf::from(backup)
+    };
+    // Earlier backups are kept, the new one gets the first free `.N` suffix.
+    let mut unique = backup.clone();
+    let mut n = 1;
+    while unique.symlink_metadata().is_ok() {
+        let mut name = backup.as_os_str().to_owned();
+        name.push(format!(".{n}"));
+        unique = PathBuf::from(name);
+        n += 1;
+    }
+    Ok(unique)
+}
+
+fn move_path(from: &Path, to: &Path) -> error::Result<()> {
+    let parent_dir = to
+        .parent()
+        .ok_or(format!("could not access parent dir of {}", to.display()))?;
+    if !parent_dir.exists() {
+        fs::create_dir_all(parent_dir)
+            .map_err(|err| format!("could not create dir {}: {err}", parent_dir.display()))?;
+    }
+    fs::rename(from, to).map_err(|err| {
+        format!(
+            "could not move {} to {}: {err}",
+            from.display(),
+            to.display()
+        )
+    })?;
+    Ok(())
+}
+
+fn copy_path(from: &Path, to: &Path) -> error::Result<()> {
+    let parent_dir = to
+        .parent()
+        .ok_or(format!("could not access parent dir of {}", to.display()))?;
+    if !parent_dir.exists() {
+        fs::create_dir_all(parent_dir)
+            .map_err(|err| format!("could not create dir {}: {err}", parent_dir.display()))?;
+    }
+    fs::copy(from, to).map_err(|err| {
+        format!(
+            "could not copy {} to {}: {err}",
+            from.display(),
+            to.display()
+        )
+    })?;
+    Ok(())
+}
+
+fn remove_path(path: &Path) -> error::Result<()> {
+    if path.is_dir() && !path.is_symlink() {
+        fs::remove_dir_all(path)
+            .map_err(|err| format!("could not remove dir {}: {err}", path.display()))?;
+    } else {
+        fs::remove_file(path)
+            .map_err(|err| format!("could not remove file {}: {err}", path.display()))?;
+    }
     Ok(())
 }
 
@@ -338,11 +1844,144 @@ fn generate_template(
     template: impl AsRef<Path>,
     config: &VarMap,
     template_engine: &mut upon::Engine,
-) -> error::Result<()> {
+    name: &str,
+    opts: Options,
+    lockfile: &mut Lockfile,
+) -> error::Result<bool> {
+    let (template, rendered) = render_template(template, config, template_engine)?;
+    let dest = resolve_home_dir(dest.as_ref())?;
+    let entry = lockfile::Entry {
+        name: name.to_string(),
+        kind: lockfile::Kind::Generated,
+        source: template.clone(),
+        hash: lockfile::hash_bytes(rendered.as_bytes()),
+        backup: None,
+    };
+
+    let mut entry = entry;
+    let generated = lockfile
+        .get(&dest)
+        .filter(|entry| entry.kind == lockfile::Kind::Generated)
+        .cloned();
+
+    let current = fs::read(&dest).ok();
+    if current.as_deref() == Some(rendered.as_bytes()) {
+        log!(Info, "Skipped generating {}. Up to date.", dest.display());
+        entry.backup = generated.and_then(|generated| generated.backup);
+        lockfile.insert(&dest, entry)?;
+        return Ok(false);
+    }
+
+    let modified = match (&current, &generated) {
+        (Some(current), Some(generated)) => lockfile::hash_bytes(current) != generated.hash,
+        _ => false,
+    };
+    if modified && !opts.force && !opts.backup {
+        log!(
+            Warning,
+            "Destination {} was modified since it was generated. Resolve manually",
+            dest.display()
+        );
+        return Ok(false);
+    }
+
+    if opts.dry_run {
+        if modified && opts.backup {
+            log!(
+                Warning,
+                "Destination {} was modified since it was generated. Would back up to {}",
+                dest.display(),
+                backup_path(&dest, opts)?.display()
+            );
+        } else if modified {
+            log!(
+                Warning,
+                "Destination {} was modified since it was generated. Would overwrite",
+                dest.display()
+            );
+        }
+        log!(
+            Info,
+            "Would generate template {} into {}",
+            template.display(),
+            dest.display()
+        );
+        return Ok(true);
+    }
+
+    if modified && opts.backup {
+        let backup = backup_path(&dest, opts)?;
+        log!(
+            Warning,
+            "Destination {} was modified since it was generated. Backing up to {}",
+            dest.display(),
+            backup.display()
+        );
+        copy_path(&dest, &backup)?;
+        entry.backup = Some(backup);
+    } else if modified {
+        log!(
+            Warning,
+            "Destination {} was modified since it was generated. Overwriting",
+            dest.display()
+        );
+    }
+    if entry.backup.is_none() {
+        entry.backup = generated.and_then(|generated| generated.backup);
+    }
+
+    write_atomic(&dest, rendered.as_bytes())?;
+    log!(Info, "Generated template {}", template.display());
+    lockfile.insert(&dest, entry)?;
+    Ok(true)
+}
+
+/// Writes to a temporary file next to `path` and renames it over `path`, following symlinks.
+fn write_atomic(path: &Path, data: &[u8]) -> error::Result<()> {
+    let path = if path.is_symlink() {
+        path.canonicalize()
+            .map_err(|err| format!("could not resolve symlink {}: {err}", path.display()))?
+    } else {
+        path.to_path_buf()
+    };
+    let file_name = path
+        .file_name()
+        .ok_or(format!("could not extract file_name of {}", path.display()))?;
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(file_name);
+    // Unique per process, as `watch` and `wallpaper` may write the same cache file at once.
+    tmp_name.push(format!(".{}.dotcraft-tmp", std::process::id()));
+    let tmp_path = path.with_file_name(tmp_name);
+
+    // Flushed before the rename, or a crash could leave an empty file behind the new name.
+    let write = || -> io::Result<()> {
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(data)?;
+        file.sync_all()
+    };
+    write().map_err(|err| {
+        let _ = fs::remove_file(&tmp_path);
+        format!("could not write to {}: {err}", tmp_path.display())
+    })?;
+    if let Ok(metadata) = path.metadata() {
+        fs::set_permissions(&tmp_path, metadata.permissions())
+            .map_err(|err| format!("could not set permissions of {}: {err}", tmp_path.display()))?;
+    }
+    fs::rename(&tmp_path, &path).map_err(|err| {
+        let _ = fs::remove_file(&tmp_path);
+        format!("could not write to {}: {err}", path.display())
+    })?;
+    Ok(())
+}
+
+fn render_template(
+    template: impl AsRef<Path>,
+    config: &VarMap,
+    template_engine: &mut upon::Engine,
+) -> error::Result<(PathBuf, String)> {
     let template = resolve_home_dir(template.as_ref())?
         .canonicalize()
         .map_err(|err| format!("could not find {}: {err}", template.as_ref().display()))?;
-    let dest = resolve_home_dir(dest.as_ref())?;
 
     let data = fs::read_to_string(&template)
         .map_err(|err| format!("could not read file {}: {err}", template.display()))?;
@@ -354,8 +1993,247 @@ fn generate_template(
         .to_string()
         .map_err(|err| format!("could not render template {}: {err}", template.display()))?;
 
-    fs::write(&dest, rendered)
-        .map_err(|err| format!("could not write to {}: {err}", dest.display()))?;
-    log!(Info, "Generated template {}", template.display());
+    Ok((template, rendered))
+}
+
+fn record_link(
+    target: &Path,
+    dest: &Path,
+    name: &str,
+    backup: Option<PathBuf>,
+    lockfile: &mut Lockfile,
+) -> error::Result<()> {
+    let backup = backup.or_else(|| lockfile.get(dest).and_then(|entry| entry.backup.clone()));
+    lockfile.insert(
+        dest,
+        lockfile::Entry {
+            name: name.to_string(),
+            kind: lockfile::Kind::Link,
+            source: target.to_path_buf(),
+            // Only copies and generated files are hashed by content. A linked directory may be
+            // huge or contain symlink loops.
+            hash: lockfile::hash_bytes(target.as_os_str().as_encoded_bytes()),
+            backup,
+        },
+    )
+}
+
+fn record_copy(
+    target: &Path,
+    dest: &Path,
+    name: &str,
+    hash: String,
+    backup: Option<PathBuf>,
+    lockfile: &mut Lockfile,
+) -> error::Result<()> {
+    let backup = backup.or_else(|| lockfile.get(dest).and_then(|entry| entry.backup.clone()));
+    lockfile.insert(
+        dest,
+        lockfile::Entry {
+            name: name.to_string(),
+            kind: lockfile::Kind::Copy,
+            source: target.to_path_buf(),
+            hash,
+            backup,
+        },
+    )
+}
+
+fn restore_file(
+    dest: &Path,
+    entry: &lockfile::Entry,
+    opts: Options,
+    lockfile: &mut Lockfile,
+) -> error::Result<()> {
+    let Some(backup) = &entry.backup else {
+        return Ok(());
+    };
+    // Links are always ours, copies and generated files only while nobody touched them.
+    let owned = dest.is_symlink()
+        || (entry.kind != lockfile::Kind::Link
+            && dest.is_file()
+            && lockfile::hash_path(dest)? == entry.hash);
+    if backup.symlink_metadata().is_err() {
+        log!(
+            Warning,
+            "Backup {} of {} no longer exists. Skipping",
+            backup.display(),
+            dest.display()
+        );
+        return Ok(());
+    }
+    if !owned && dest.exists() {
+        log!(
+            Warning,
+            "Destination {} was modified by someone else. Resolve manually",
+            dest.display()
+        );
+        return Ok(());
+    }
+    if opts.dry_run {
+        log!(
+            Info,
+            "Would restore {} from {}",
+            dest.display(),
+            backup.display()
+        );
+        return Ok(());
+    }
+    if owned {
+        fs::remove_file(dest)
+            .map_err(|err| format!("could not remove file {}: {err}", dest.display()))?;
+    }
+    move_path(backup, dest)?;
+    log!(
+        Info,
+        "Restored {} from {}",
+        dest.display(),
+        backup.display()
+    );
+    lockfile.remove(dest);
+    Ok(())
+}
+
+fn unlink_dir_all(
+    target: impl AsRef<Path>,
+    dest: impl AsRef<Path>,
+    recursive: bool,
+    mode: Mode,
+    root: &Path,
+    opts: Options,
+    lockfile: &mut Lockfile,
+) -> error::Result<()> {
+    let target = resolve_home_dir(&target)?;
+    let dest = resolve_home_dir(dest)?;
+
+    if target.is_dir() && (recursive || mode == Mode::Copy) && !dest.is_symlink() {
+        if !dest.is_dir() {
+            return Ok(());
+        }
+        for entry in fs::read_dir(&target)
+            .map_err(|err| format!("could not read dir {}: {err}", target.display()))?
+        {
+            let entry = entry?;
+            let dest = dest.join(entry.path().file_name().ok_or(format!(
+                "could not extract file_name of {}",
+                entry.path().display()
+            ))?);
+            unlink_dir_all(entry.path(), dest, recursive, mode, root, opts, lockfile)?;
+        }
+    } else {
+        unlink_file(&dest, root, opts, lockfile)?;
+    }
+    Ok(())
+}
+
+fn unlink_file(
+    dest: impl AsRef<Path>,
+    root: &Path,
+    opts: Options,
+    lockfile: &mut Lockfile,
+) -> error::Result<()> {
+    let dest = dest.as_ref();
+
+    if let Some(entry) = lockfile.get(dest).cloned() {
+        if entry.kind == lockfile::Kind::Copy {
+            return remove_copy(dest, &entry, opts, lockfile);
+        }
+        if entry.kind == lockfile::Kind::Link && entry.backup.is_none() {
+            lockfile.remove(dest);
+        }
+    }
+
+    if !dest.is_symlink() {
+        if dest.exists() {
+            log!(
+                Warning,
+                "Destination {} exists but it's not a symlink. Skipping",
+                dest.display()
+            );
+        }
+        return Ok(());
+    }
+
+    let origin = fs::read_link(dest)
+        .map_err(|err| format!("could not read symlink {}: {err}", dest.display()))?;
+    let origin = match dest.parent() {
+        Some(parent) if origin.is_relative() => parent.join(origin),
+        _ => origin,
+    };
+    let origin = origin.canonicalize().unwrap_or(origin);
+
+    if origin.starts_with(root) && opts.dry_run {
+        log!(Info, "Would remove symlink {}", dest.display());
+    } else if origin.starts_with(root) {
+        fs::remove_file(dest)
+            .map_err(|err| format!("could not remove file {}: {err}", dest.display()))?;
+        log!(Info, "Removed symlink {}", dest.display());
+    } else {
+        log!(
+            Warning,
+            "Destination {} is symlinked to {}. Skipping",
+            dest.display(),
+            origin.display()
+        );
+    }
+    Ok(())
+}
+
+fn remove_copy(
+    dest: &Path,
+    entry: &lockfile::Entry,
+    opts: Options,
+    lockfile: &mut Lockfile,
+) -> error::Result<()> {
+    if dest.symlink_metadata().is_ok() {
+        if dest.is_symlink() || !dest.is_file() || lockfile::hash_path(dest)? != entry.hash {
+            log!(
+                Warning,
+                "Destination {} was modified since it was copied. Skipping",
+                dest.display()
+            );
+            return Ok(());
+        }
+        if opts.dry_run {
+            log!(Info, "Would remove copy {}", dest.display());
+            return Ok(());
+        }
+        fs::remove_file(dest)
+            .map_err(|err| format!("could not remove file {}: {err}", dest.display()))?;
+        log!(Info, "Removed copy {}", dest.display());
+    }
+    if entry.backup.is_none() {
+        lockfile.remove(dest);
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_link_path() {
+        // Made-up paths, so that `canonicalize` leaves them untouched.
+        let cases = [
+            (
+                "/dotcraft-test/dots/kitty",
+                "/dotcraft-test/.config/kitty",
+                "../dots/kitty",
+            ),
+            ("/dotcraft-test/dots/a/b", "/dotcraft-test/dots/c", "a/b"),
+            ("/dotcraft-test/a", "/dotcraft-test/x/y/z/a", "../../../a"),
+            ("/other/a", "/dotcraft-test/a", "../other/a"),
+        ];
+        for (target, dest, expected) in cases {
+            let link = link_path(Path::new(target), Path::new(dest), true).unwrap();
+            assert_eq!(link, Path::new(expected), "{target} {dest}");
+        }
+    }
+
+    #[test]
+    fn absolute_link_path() {
+        let link = link_path(Path::new("/a/b"), Path::new("/c/d"), false).unwrap();
+        assert_eq!(link, Path::new("/a/b"));
+    }
+}
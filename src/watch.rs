@@ -0,0 +1,170 @@
+use crate::error::Result;
+
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::{CString, OsStr, OsString},
+    fs, io,
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd},
+        unix::ffi::OsStrExt,
+    },
+    path::Path,
+};
+
+const EVENTS: u32 = libc::IN_CLOSE_WRITE
+    | libc::IN_CREATE
+    | libc::IN_DELETE
+    | libc::IN_MOVED_FROM
+    | libc::IN_MOVED_TO;
+
+/// How long the watched paths have to stay quiet before `wait` returns.
+const DEBOUNCE_MS: i32 = 200;
+
+/// Watches files through their parent directory, so that editors replacing a file on save do not
+/// silently drop the watch.
+pub struct Watcher {
+    fd: OwnedFd,
+    /// Names of interest per watch descriptor. `None` means any change in the directory.
+    watches: HashMap<i32, Option<HashSet<OsString>>>,
+    count: usize,
+}
+
+impl Watcher {
+    pub fn new() -> Result<Self> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(format!(
+                "could not initialize inotify: {}",
+                io::Error::last_os_error()
+            )
+            .into());
+        }
+        Ok(Self {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+            watches: HashMap::new(),
+            count: 0,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Watches a single file, which does not need to exist yet.
+    pub fn add_file(&mut self, path: &Path) -> Result<()> {
+        let path = std::path::absolute(path)
+            .map_err(|err| format!("invalid path {}: {err}", path.display()))?;
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            return Err(format!("could not watch {}", path.display()).into());
+        };
+        let wd = self.add_watch(parent)?;
+        if let Some(names) = self.watches.entry(wd).or_insert(Some(HashSet::new())) {
+            names.insert(name.to_os_string());
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Watches every change inside `path` and its subdirectories.
+    pub fn add_dir_all(&mut self, path: &Path) -> Result<()> {
+        let wd = self.add_watch(path)?;
+        self.watches.insert(wd, None);
+        self.count += 1;
+        for entry in fs::read_dir(path)
+            .map_err(|err| format!("could not read dir {}: {err}", path.display()))?
+        {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                self.add_dir_all(&entry.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn add_watch(&self, dir: &Path) -> Result<i32> {
+        let c_path = CString::new(dir.as_os_str().as_bytes())
+            .map_err(|err| format!("invalid path {}: {err}", dir.display()))?;
+        let wd = unsafe { libc::inotify_add_watch(self.fd.as_raw_fd(), c_path.as_ptr(), EVENTS) };
+        if wd < 0 {
+            return Err(format!(
+                "could not watch {}: {}",
+                dir.display(),
+                io::Error::last_os_error()
+            )
+            .into());
+        }
+        Ok(wd)
+    }
+
+    /// Blocks until one of the watched paths changes and stays quiet for `DEBOUNCE_MS`.
+    pub fn wait(&self) -> Result<()> {
+        while !self.read_events()? {}
+        while self.poll(DEBOUNCE_MS)? {
+            self.read_events()?;
+        }
+        Ok(())
+    }
+
+    fn poll(&self, timeout_ms: i32) -> Result<bool> {
+        let mut pollfd = libc::pollfd {
+            fd: self.fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let ready = unsafe { libc::poll(&mut pollfd, 1, timeout_ms) };
+        if ready < 0 {
+            return Err(format!("could not poll inotify: {}", io::Error::last_os_error()).into());
+        }
+        Ok(ready > 0)
+    }
+
+    /// Returns whether any of the events concerns a watched path.
+    fn read_events(&self) -> Result<bool> {
+        let mut buffer = [0u8; 4096];
+        let len = unsafe {
+            libc::read(
+                self.fd.as_raw_fd(),
+                buffer.as_mut_ptr() as *mut libc::c_void,
+                buffer.len(),
+            )
+        };
+        if len < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                return Ok(false);
+            }
+            return Err(format!("could not read inotify events: {err}").into());
+        }
+
+        // struct inotify_event { int wd; uint32_t mask; uint32_t cookie; uint32_t len; char name[]; }
+        let header = std::mem::size_of::<libc::inotify_event>();
+        let mut changed = false;
+        let mut offset = 0;
+        while offset + header <= len as usize {
+            let field = |i: usize| {
+                let start = offset + i * 4;
+                [
+                    buffer[start],
+                    buffer[start + 1],
+                    buffer[start + 2],
+                    buffer[start + 3],
+                ]
+            };
+            let wd = i32::from_ne_bytes(field(0));
+            let mask = u32::from_ne_bytes(field(1));
+            let name_len = u32::from_ne_bytes(field(3)) as usize;
+            let name = &buffer[offset + header..offset + header + name_len];
+            let name = OsStr::from_bytes(name.split(|&b| b == 0).next().unwrap_or_default());
+            offset += header + name_len;
+
+            // `IN_IGNORED` means the watched directory itself is gone and the watches need rebuilding.
+            changed |= mask & libc::IN_IGNORED != 0
+                || match self.watches.get(&wd) {
+                    Some(None) => true,
+                    Some(Some(names)) => names.contains(name),
+                    None => false,
+                };
+        }
+        Ok(changed)
+    }
+}